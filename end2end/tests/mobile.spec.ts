@@ -0,0 +1,106 @@
+import { test, expect, type Page } from "@playwright/test";
+
+// About the size of a phone held upright.
+test.use({ viewport: { width: 390, height: 844 }, hasTouch: true });
+
+async function expectNoSidewaysScrolling(page: Page) {
+  const overflow = await page.evaluate(
+    () => document.documentElement.scrollWidth - document.documentElement.clientWidth,
+  );
+  expect(overflow).toBeLessThanOrEqual(0);
+}
+
+// The login tests expect that nobody registered "someone", unknown names get the normal start position.
+const UNKNOWN_USER = "someone";
+
+test("login board fits the screen and stays square", async ({ page }) => {
+  await page.goto("http://localhost:3000/login");
+
+  await page.getByPlaceholder("Name").fill(UNKNOWN_USER);
+  await page.getByRole("button", { name: "Continue" }).click();
+
+  const board = page.getByRole("application");
+  await expect(board).toBeVisible();
+
+  const box = await board.boundingBox();
+  expect(box).not.toBeNull();
+  expect(box!.x).toBeGreaterThanOrEqual(0);
+  expect(box!.x + box!.width).toBeLessThanOrEqual(390);
+  expect(Math.abs(box!.width - box!.height)).toBeLessThan(1);
+
+  await expectNoSidewaysScrolling(page);
+});
+
+test("a square can be tapped on the phone sized board", async ({ page }) => {
+  await page.goto("http://localhost:3000/login");
+
+  await page.getByPlaceholder("Name").fill(UNKNOWN_USER);
+  await page.getByRole("button", { name: "Continue" }).click();
+
+  await page.getByRole("img", { name: "white pawn on e2" }).tap();
+  await expect(page.locator('[data-square="e3"]')).toBeVisible();
+  await expect(page.locator('[data-square="e4"]')).toBeVisible();
+
+  await page.locator('[data-square="e4"]').tap();
+  await expect(page.getByRole("img", { name: "white pawn on e4" })).toBeVisible();
+  await expect(page.getByRole("img", { name: "white pawn on e2" })).toHaveCount(0);
+});
+
+test("register page has no sideways scrolling", async ({ page }) => {
+  await page.goto("http://localhost:3000/register");
+
+  await expect(page.getByPlaceholder("Name")).toBeVisible();
+  await expectNoSidewaysScrolling(page);
+});
+
+test("sidebar turns into a bar along the bottom", async ({ page }) => {
+  await page.goto("http://localhost:3000/feed");
+
+  const nav = page.getByRole("navigation", { name: "Main" });
+  await expect(nav).toBeVisible();
+  await expect(nav.getByRole("link", { name: "Play" })).toBeVisible();
+  await expect(nav.getByRole("link", { name: "Feed" })).toBeVisible();
+
+  const box = await nav.boundingBox();
+  expect(box).not.toBeNull();
+  expect(Math.round(box!.width)).toBe(390);
+  expect(Math.round(box!.y + box!.height)).toBe(844);
+
+  await expectNoSidewaysScrolling(page);
+});
+
+// Long enough and off the opening book, so it passes the default strength check.
+const PASSWORD_PGN = "1. a3 h6 2. b3 g6 3. c3 f6 4. d3 e6 5. e3 d6 6. f3 c6";
+
+async function register(page: Page) {
+  await page.goto("http://localhost:3000/register");
+
+  await page.getByPlaceholder("Name").fill(`phone-${Date.now()}`);
+  await page.getByRole("button", { name: "Continue" }).click();
+
+  // Once to set the game, once to confirm it.
+  for (let i = 0; i < 2; i++) {
+    await page.getByPlaceholder("1. e4 e5 2. Nf3 Nc6 ...").fill(PASSWORD_PGN);
+    await page.getByRole("button", { name: "Use PGN" }).click();
+  }
+
+  await expect(page.getByText("User created")).toBeVisible();
+}
+
+test("the two pieces to vote on are stacked", async ({ page }) => {
+  await register(page);
+  await page.goto("http://localhost:3000/play");
+
+  const pieces = page.getByText("OR", { exact: true }).locator("..").locator("img");
+  await expect(pieces).toHaveCount(2);
+
+  const first = await pieces.nth(0).boundingBox();
+  const second = await pieces.nth(1).boundingBox();
+  expect(first).not.toBeNull();
+  expect(second).not.toBeNull();
+  // One above the other, not side by side.
+  expect(second!.y).toBeGreaterThanOrEqual(first!.y + first!.height);
+  expect(Math.abs(second!.x - first!.x)).toBeLessThan(1);
+
+  await expectNoSidewaysScrolling(page);
+});
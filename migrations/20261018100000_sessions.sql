@@ -0,0 +1,9 @@
+CREATE TABLE sessions (
+    id VARCHAR PRIMARY KEY,
+    user_id VARCHAR REFERENCES users(id) NOT NULL,
+    token_hash bytea NOT NULL UNIQUE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX sessions_user_id_idx ON sessions (user_id);
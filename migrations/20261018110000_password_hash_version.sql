@@ -0,0 +1,4 @@
+ALTER TABLE users ADD COLUMN hash_version SMALLINT NOT NULL DEFAULT 1;
+ALTER TABLE users ADD COLUMN password_hash VARCHAR;
+ALTER TABLE users ALTER COLUMN password DROP NOT NULL;
+ALTER TABLE users ALTER COLUMN salt DROP NOT NULL;
@@ -0,0 +1,9 @@
+CREATE TABLE recovery_codes (
+    id VARCHAR PRIMARY KEY,
+    user_id VARCHAR REFERENCES users(id) NOT NULL,
+    code_hash bytea NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX recovery_codes_user_id_idx ON recovery_codes (user_id);
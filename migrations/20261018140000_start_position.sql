@@ -0,0 +1,2 @@
+-- NULL is the normal start position, everything else is a FEN read with Chess960 castling.
+ALTER TABLE users ADD COLUMN start_fen VARCHAR;
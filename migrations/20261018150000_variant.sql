@@ -0,0 +1,2 @@
+-- UCI name of the variant the password game is played in, see shakmaty::variant::Variant::uci.
+ALTER TABLE users ADD COLUMN variant VARCHAR NOT NULL DEFAULT 'chess';
@@ -0,0 +1,2 @@
+ALTER TABLE sessions ADD COLUMN last_seen_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL;
+ALTER TABLE sessions ADD COLUMN user_agent VARCHAR;
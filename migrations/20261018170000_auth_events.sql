@@ -0,0 +1,33 @@
+CREATE TYPE auth_event_kind AS ENUM ('registration', 'login_success', 'wrong_password', 'unknown_user', 'lockout');
+
+CREATE TABLE auth_events (
+    id VARCHAR PRIMARY KEY,
+    -- NULL for names that don't belong to any user.
+    user_id VARCHAR REFERENCES users(id),
+    username VARCHAR NOT NULL,
+    kind auth_event_kind NOT NULL,
+    ip VARCHAR,
+    user_agent VARCHAR,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX auth_events_user_id_idx ON auth_events (user_id, created_at);
+CREATE INDEX auth_events_ip_idx ON auth_events (ip, created_at);
+
+-- For admins, run `SELECT * FROM credential_stuffing_suspects` to find addresses
+-- that failed logins to many different names in the last day.
+CREATE VIEW credential_stuffing_suspects AS
+SELECT
+    ip,
+    COUNT(DISTINCT username) AS usernames,
+    COUNT(*) FILTER (WHERE kind = 'unknown_user') AS unknown_users,
+    COUNT(*) FILTER (WHERE kind = 'wrong_password') AS wrong_passwords,
+    COUNT(*) FILTER (WHERE kind = 'lockout') AS lockouts,
+    MIN(created_at) AS first_seen_at,
+    MAX(created_at) AS last_seen_at
+FROM auth_events
+WHERE kind IN ('wrong_password', 'unknown_user', 'lockout')
+    AND created_at > NOW() - INTERVAL '1 day'
+GROUP BY ip
+HAVING COUNT(DISTINCT username) >= 5
+ORDER BY usernames DESC;
@@ -0,0 +1,7 @@
+-- The secret is kept while enrolling, but the second factor is only asked for once it is enabled.
+ALTER TABLE users ADD COLUMN totp_secret bytea;
+ALTER TABLE users ADD COLUMN totp_enabled BOOLEAN DEFAULT FALSE NOT NULL;
+-- The last time step a code was accepted for, so a code can't be used twice.
+ALTER TABLE users ADD COLUMN totp_last_step BIGINT;
+
+ALTER TYPE auth_event_kind ADD VALUE 'wrong_second_factor';
@@ -0,0 +1,17 @@
+-- Votes of deleted users can stay on the feed without a name.
+ALTER TABLE votes ALTER COLUMN user_id DROP NOT NULL;
+ALTER TABLE votes DROP CONSTRAINT votes_user_id_fkey;
+ALTER TABLE votes ADD CONSTRAINT votes_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE SET NULL;
+
+ALTER TABLE sessions DROP CONSTRAINT sessions_user_id_fkey;
+ALTER TABLE sessions ADD CONSTRAINT sessions_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+ALTER TABLE recovery_codes DROP CONSTRAINT recovery_codes_user_id_fkey;
+ALTER TABLE recovery_codes ADD CONSTRAINT recovery_codes_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+ALTER TABLE auth_events DROP CONSTRAINT auth_events_user_id_fkey;
+ALTER TABLE auth_events ADD CONSTRAINT auth_events_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
@@ -0,0 +1,21 @@
+-- New names are trimmed and normalised by the app, old ones at least lose their outer spaces.
+-- Names that would then only differ in case can't all stay, or the index below fails. Of every
+-- such group one name is kept, preferring one that needed no trimming, and the others get the
+-- start of their id appended, like "Magnus-k3x9f2ab". Run the SELECT of `ranked` on its own
+-- before deploying to see who gets renamed.
+WITH ranked AS (
+    SELECT
+        id,
+        ROW_NUMBER() OVER (
+            PARTITION BY LOWER(btrim(username))
+            ORDER BY username = btrim(username) DESC, id
+        ) AS rank
+    FROM users
+)
+UPDATE users SET username = left(btrim(username), 91) || '-' || left(users.id, 8)
+FROM ranked
+WHERE ranked.id = users.id AND ranked.rank > 1;
+
+UPDATE users SET username = btrim(username) WHERE username <> btrim(username);
+
+CREATE UNIQUE INDEX users_username_lower_idx ON users (LOWER(username));
@@ -0,0 +1 @@
+ALTER TYPE auth_event_kind ADD VALUE 'recovery_codes_regenerated';
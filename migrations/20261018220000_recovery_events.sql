@@ -0,0 +1,2 @@
+ALTER TYPE auth_event_kind ADD VALUE 'wrong_recovery_code';
+ALTER TYPE auth_event_kind ADD VALUE 'account_recovered';
@@ -0,0 +1,2 @@
+ALTER TYPE auth_event_kind ADD VALUE 'password_changed';
+ALTER TYPE auth_event_kind ADD VALUE 'account_deleted';
@@ -0,0 +1,299 @@
+use leptos::either::EitherOf3;
+use leptos::logging::*;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use leptos_router::hooks::use_navigate;
+use leptos_router::NavigateOptions;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use shakmaty::fen::*;
+use shakmaty::san::*;
+
+use crate::app::game_modal::*;
+use crate::app::login::get_start_position;
+use crate::app::password::*;
+use crate::app::sidebar::get_user_name;
+use crate::types::start::StartPosition;
+use crate::types::{DataExport, Error};
+
+#[component]
+pub fn ExportDataPage() -> impl IntoView {
+    let (export, set_export) = signal(None::<Result<String, Error>>);
+
+    let on_export = move |_| {
+        spawn_local(async move {
+            let json = export_my_data().await.and_then(|data| {
+                serde_json::to_string_pretty(&data).map_err(|e| Error::Encode(e.to_string()))
+            });
+            set_export.set(Some(json));
+        })
+    };
+
+    let export_view = move || match export.get() {
+        None => EitherOf3::A(view! {
+            <button on:click=on_export class="p-4 text-2xl button-primary w-fit">
+                "Export"
+            </button>
+        }),
+        Some(Ok(json)) => {
+            let href = format!(
+                "data:application/json;charset=utf-8,{}",
+                utf8_percent_encode(&json, NON_ALPHANUMERIC)
+            );
+            EitherOf3::B(view! {
+                <a href=href download="chess-or-pass-data.json" class="p-4 text-2xl button-primary w-fit">
+                    "Download"
+                </a>
+                <pre class="overflow-scroll p-4 w-full font-mono text-sm rounded-2xl bg-secondary">
+                    {json}
+                </pre>
+            })
+        }
+        Some(Err(e)) => EitherOf3::C(
+            view! { <div class="text-red-700">"Error exporting your data: " {e.to_string()}</div> },
+        ),
+    };
+
+    view! {
+        <div class="flex overflow-scroll flex-col gap-4 justify-start p-4 w-full h-full">
+            <span class="w-full text-3xl h-fit">"Export my data"</span>
+            <span class="font-sans text-lg font-light">
+                "Your profile and every vote you cast, as JSON. Your password game is never stored, only a hash of it."
+            </span>
+            {export_view}
+        </div>
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum State {
+    Votes,
+    Password {
+        delete_votes: bool,
+    },
+    Done {
+        delete_votes: bool,
+        password: Vec<(San, Fen)>,
+    },
+}
+
+#[component]
+pub fn DeleteAccountPage() -> impl IntoView {
+    let user = Resource::new(
+        || (),
+        |_| async move {
+            match get_user_name().await? {
+                Some(name) => {
+                    let start = get_start_position(name.clone()).await?;
+                    Ok(Some((name, start)))
+                }
+                None => Ok::<_, Error>(None),
+            }
+        },
+    );
+
+    Effect::new(move || {
+        if let Some(Ok(None)) = user.get() {
+            use_navigate()("/login-register", NavigateOptions::default());
+        }
+    });
+
+    let suspense = move || {
+        Suspend::new(async move {
+            match user.await {
+                Ok(Some((user_name, start))) => {
+                    EitherOf3::A(view! { <DeleteAccount user_name start /> })
+                }
+                Ok(None) => EitherOf3::B(view! { <div>"Not logged in"</div> }),
+                Err(e) => EitherOf3::C(
+                    view! { <div class="text-red-700">"Error loading username: " {e.to_string()}</div> },
+                ),
+            }
+        })
+    };
+
+    view! {
+        <div class="flex flex-col justify-center items-center p-5 w-full h-full">
+            <Transition fallback=move || view! { <div>"Loading..."</div> }>{suspense}</Transition>
+        </div>
+    }
+}
+
+#[component]
+fn DeleteAccount(user_name: String, start: StartPosition) -> impl IntoView {
+    let (state, set_state) = signal(State::Votes);
+
+    let current_view = move || match state.get() {
+        State::Votes => {
+            let choose =
+                move |delete_votes| move |_| set_state.set(State::Password { delete_votes });
+
+            EitherOf3::A(view! {
+                <div class="flex flex-col gap-2.5 justify-center items-center">
+                    <span class="text-5xl">"Delete account"</span>
+                    <span class="font-sans text-lg font-light">
+                        "This can't be undone. What should happen to your votes?"
+                    </span>
+                    <button on:click=choose(false) class="p-4 w-full text-2xl button-primary">
+                        "Keep them without my name"
+                    </button>
+                    <button on:click=choose(true) class="p-4 w-full text-2xl button-secondary">
+                        "Delete them too"
+                    </button>
+                </div>
+            })
+        }
+        State::Password { delete_votes } => {
+            let on_continue = move |password| {
+                set_state.set(State::Done {
+                    delete_votes,
+                    password,
+                })
+            };
+
+            EitherOf3::B(view! {
+                <PlayPassword
+                    user_name=user_name.clone()
+                    start=start.clone()
+                    intro="Replay your game to confirm that you want to delete your account."
+                    allow_pgn=true
+                    on_continue
+                />
+            })
+        }
+        State::Done {
+            delete_votes,
+            password,
+        } => {
+            let result = LocalResource::new(move || delete_account(password.clone(), delete_votes));
+
+            EitherOf3::C(view! {
+                <Suspense>
+                    {move || Suspend::new(async move {
+                        let result = result.await;
+
+                        let (main_text, sub_text, button_text) = match &result {
+                            Ok(_) => (
+                                "Account deleted".to_string(),
+                                "Thanks for playing".to_string(),
+                                "Bye!".to_string(),
+                            ),
+                            Err(Error::WrongPassword) => (
+                                "Wrong password".to_string(),
+                                "That is not your game".to_string(),
+                                "Try again".to_string(),
+                            ),
+                            Err(e) => (
+                                "Something went wrong".to_string(),
+                                e.to_string(),
+                                "Back to settings".to_string(),
+                            ),
+                        };
+
+                        let on_click = move |_| match &result {
+                            Ok(_) => go_home(),
+                            Err(Error::WrongPassword) => {
+                                set_state.set(State::Password { delete_votes })
+                            }
+                            Err(_) => use_navigate()("/settings", NavigateOptions::default()),
+                        };
+
+                        view! { <GameModal visible=true main_text sub_text button_text on_click /> }
+                    })}
+                </Suspense>
+            })
+        }
+    };
+
+    view! { {current_view} }
+}
+
+/// A full page load, so the sidebar forgets the deleted user.
+fn go_home() {
+    if let Some(w) = leptos::web_sys::window() {
+        if let Err(e) = w.location().set_href("/") {
+            error!("Error leaving the deleted account: {e:?}");
+        }
+    }
+}
+
+#[server]
+async fn export_my_data() -> Result<DataExport, Error> {
+    use crate::types::{AppState, Pieces, Profile, Session, Vote};
+    let app_state = expect_context::<AppState>();
+
+    let session = Session::require(&app_state).await?;
+
+    let profile = sqlx::query_as!(
+        Profile,
+        r#"
+        SELECT id, username, variant, start_fen, totp_enabled as two_factor_enabled
+        FROM users WHERE id = $1
+        "#,
+        session.user_id
+    )
+    .fetch_one(&app_state.db.pool)
+    .await?;
+
+    let votes = sqlx::query_as!(
+        Vote,
+        r#"
+        SELECT votes.id, users.username, votes.first_piece as "first_piece: Pieces", votes.second_piece as "second_piece: Pieces", votes.voted_for_first, votes.reason, votes.created_at
+        FROM votes
+        JOIN users ON votes.user_id = users.id
+        WHERE votes.user_id = $1
+        ORDER BY votes.created_at
+        "#,
+        session.user_id
+    )
+    .fetch_all(&app_state.db.pool)
+    .await?;
+
+    Ok(DataExport { profile, votes })
+}
+
+/// Deletes the user after they replayed their game. Sessions, recovery codes and the security
+/// history go with it, the votes are either deleted or kept without a user.
+#[server]
+async fn delete_account(password: Vec<(San, Fen)>, delete_votes: bool) -> Result<(), Error> {
+    use crate::app::login::{reauthenticate, SecondFactor};
+    use crate::types::{record_auth_event, AppState, AuthEventKind};
+    let app_state = expect_context::<AppState>();
+
+    let session = reauthenticate(&app_state, &password, SecondFactor::Skip).await?;
+
+    let mut transaction = app_state.db.pool.begin().await?;
+
+    let user = sqlx::query!("SELECT username FROM users WHERE id = $1", session.user_id)
+        .fetch_one(&mut *transaction)
+        .await?;
+
+    if delete_votes {
+        sqlx::query!("DELETE FROM votes WHERE user_id = $1", session.user_id)
+            .execute(&mut *transaction)
+            .await?;
+    } else {
+        sqlx::query!(
+            "UPDATE votes SET user_id = NULL WHERE user_id = $1",
+            session.user_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+
+    sqlx::query!("DELETE FROM users WHERE id = $1", session.user_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    // Without the user, the rest of the history is gone, this one only keeps the name for admins.
+    record_auth_event(
+        &app_state.db,
+        None,
+        &user.username,
+        AuthEventKind::AccountDeleted,
+    )
+    .await?;
+
+    session.revoke(&app_state).await
+}
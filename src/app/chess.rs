@@ -1,31 +1,163 @@
+use crate::types::start::StartPosition;
 use leptos::either::Either;
+use leptos::ev::{KeyboardEvent, PointerEvent};
 use leptos::logging::*;
 use leptos::prelude::*;
 use shakmaty::fen::*;
 use shakmaty::san::*;
+use shakmaty::uci::UciMove;
+use shakmaty::variant::VariantPosition;
 use shakmaty::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Which side of the board is drawn at the bottom.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Orientation {
+    #[default]
+    White,
+    Black,
+    /// Whoever moves next, so the board turns around after every move.
+    SideToMove,
+}
+
+impl From<Color> for Orientation {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::White => Orientation::White,
+            Color::Black => Orientation::Black,
+        }
+    }
+}
+
+/// A piece on the board that keeps its `id` while it moves, so its view can slide along.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TrackedPiece {
+    id: u32,
+    piece: Piece,
+    square: Square,
+}
+
+/// A piece that is being dragged, with where the pointer is on the page.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Drag {
+    from: Square,
+    piece: Piece,
+    x: f64,
+    y: f64,
+    /// The width of a square, so the dragged piece keeps its size.
+    size: f64,
+    /// Only once the pointer moved is it a drag, before that it is still a click.
+    moved: bool,
+    /// Clicking a selected piece again deselects it.
+    was_selected: bool,
+}
+
+/// Numbers the boards, so every cursor gets its own id for `aria-activedescendant` to point at.
+static NEXT_BOARD_ID: AtomicUsize = AtomicUsize::new(0);
 
 #[component]
 pub fn ChessBoard(
-    on_finished: impl Fn(KnownOutcome) + 'static,
+    /// Called with the outcome after every move, `None` while the game is still going.
+    on_finished: impl Fn(Option<KnownOutcome>) + 'static,
+    /// Undo pops the last move off `notation`, and popping it from outside takes the move back as well.
     notation: RwSignal<Vec<(San, Fen)>>,
+    /// The variant and position to start from, normal chess if not set.
+    #[prop(optional)]
+    start: StartPosition,
+    /// Letters and numbers along the edges of the board.
+    #[prop(optional)]
+    coordinates: bool,
+    /// The side at the bottom, the flip button turns it around from there.
+    #[prop(optional)]
+    orientation: Orientation,
+    /// Shows an earlier position instead, counted in moves from the start, as a `MoveList` sets it.
+    /// The board takes no moves while it does, and any change to the game goes back to the newest.
+    #[prop(optional)]
+    viewing: Option<RwSignal<Option<usize>>>,
 ) -> impl IntoView {
-    let (chess, set_chess) = signal(Chess::default());
+    let viewing = viewing.unwrap_or_else(|| RwSignal::new(None));
+    let start = start.position().unwrap_or_else(|e| {
+        error!("could not set up the start position: {e}");
+        VariantPosition::new(start.variant)
+    });
+    let (chess, set_chess) = signal(start);
+    // The positions before the current one, and the moves that were taken back and can be redone.
+    let history = RwSignal::new(Vec::<VariantPosition>::new());
+    // The position that is drawn, the current one unless an earlier one is being looked at.
+    let shown = Signal::derive(move || match viewing.get() {
+        Some(plies) if plies < history.read().len() => history.read()[plies].clone(),
+        _ => chess.get(),
+    });
+    let looking_back = Memo::new(move |_| viewing.get().is_some());
+    let undone = RwSignal::new(Vec::<(San, Fen, VariantPosition)>::new());
     let current_color = Signal::derive(move || chess.read().turn());
+    let orientation = RwSignal::new(orientation);
+    let (flipped, set_flipped) = signal(false);
+    // The colour drawn at the bottom of the board.
+    let bottom = Signal::derive(move || {
+        let color = match orientation.get() {
+            Orientation::White => Color::White,
+            Orientation::Black => Color::Black,
+            Orientation::SideToMove => current_color.get(),
+        };
+        match flipped.get() {
+            true => !color,
+            false => color,
+        }
+    });
+    let last_move = Memo::new(move |_| {
+        let plies = viewing.get().unwrap_or_else(|| notation.read().len());
+        let san = notation.read().get(plies.checked_sub(1)?)?.0;
+        let before = history.read().get(plies - 1)?.clone();
+        san.to_move(&before).ok()
+    });
 
     let (selected_piece, set_selected_piece) = signal::<Option<(Square, Piece)>>(None);
+    // A piece picked out of the pocket, in Crazyhouse.
+    let (selected_drop, set_selected_drop) = signal::<Option<Role>>(None);
+    // The square a pawn is promoting on, while the piece to promote to is picked.
+    let (promoting, set_promoting) = signal::<Option<Square>>(None);
+    let drag = RwSignal::new(None::<Drag>);
+    // Where a dragged piece was let go, it is already there so it doesn't slide.
+    let dropped_on = RwSignal::new(None::<Square>);
+    let board_ref = NodeRef::<leptos::html::Div>::new();
+    // The square picked with the arrow keys, only drawn while the board has focus.
+    let cursor = RwSignal::new(Square::E2);
+    let (focused, set_focused) = signal(false);
+    let cursor_id = format!(
+        "board-cursor-{}",
+        NEXT_BOARD_ID.fetch_add(1, Ordering::Relaxed)
+    );
+    // Read out by screen readers after every move.
+    let (announcement, set_announcement) = signal(String::new());
+    let (typed_move, set_typed_move) = signal(String::new());
+    let (typed_error, set_typed_error) = signal(None::<String>);
 
     Effect::new(move |_| {
-        let c = chess.get();
-        if let Outcome::Known(k) = c.outcome() {
-            on_finished(k);
+        match chess.read().outcome() {
+            Outcome::Known(k) => on_finished(Some(k)),
+            Outcome::Unknown => on_finished(None),
         }
         set_selected_piece.set(None);
+        set_selected_drop.set(None);
+        set_promoting.set(None);
+        viewing.set(None);
+    });
+
+    // Nothing stays picked up while looking at an earlier position.
+    Effect::new(move |_| {
+        if looking_back.get() {
+            set_selected_piece.set(None);
+            set_selected_drop.set(None);
+            set_promoting.set(None);
+            drag.set(None);
+        }
     });
 
     let move_chess = {
         move |m: Move| {
             let c = chess.get();
+            let san = San::from_move(&c, m);
             let next = match c.play(m) {
                 Err(e) => {
                     error!("got error from chess: {e}");
@@ -33,78 +165,360 @@ pub fn ChessBoard(
                 }
                 Ok(c) => {
                     let fen = Fen::from_position(&c, EnPassantMode::Legal);
-                    let san = San::from_move(&c, m);
 
+                    set_announcement.set(describe_move(&c, san));
                     notation.write().push((san, fen));
                     c
                 }
             };
+            history.write().push(chess.get());
+            undone.write().clear();
             set_chess.set(next);
         }
     };
 
-    let pieces = move || {
-        let board = chess.read().board().clone();
-
-        let on_click = move |square, piece| {
-            log!("clicked on {square:?}");
-            match selected_piece.get() {
-                Some((selected_square, _selected_piece)) => {
-                    if selected_square == square {
-                        log!("set none");
-                        set_selected_piece.set(None);
-                        return;
-                    }
-                    set_selected_piece.set(Some((square, piece)))
-                }
-                None => set_selected_piece.set(Some((square, piece))),
+    let undo = move || {
+        let Some(previous) = history.write().pop() else {
+            return;
+        };
+        if let Some((san, fen)) = notation.write().pop() {
+            set_announcement.set(format!("Took back {san}"));
+            undone.write().push((san, fen, chess.get()));
+        }
+        set_chess.set(previous);
+    };
+
+    let redo = move || {
+        let Some((san, fen, next)) = undone.write().pop() else {
+            return;
+        };
+        history.write().push(chess.get());
+        set_announcement.set(describe_move(&next, san));
+        notation.write().push((san, fen));
+        set_chess.set(next);
+    };
+
+    // Plays the selected piece to `to`, or opens the role picker if it promotes there.
+    // Returns false if the piece can't go there.
+    let play_to = move |from: Square, to: Square| {
+        let legal = move_to(&chess.read(), from, to);
+        if let Some(m) = legal {
+            move_chess(m);
+            return true;
+        }
+
+        let promotes = chess
+            .read()
+            .promotion_moves()
+            .iter()
+            .any(|m| m.from() == Some(from) && m.to() == to);
+        if promotes {
+            set_promoting.set(Some(to));
+        }
+        promotes
+    };
+
+    // Moves popped off the notation from outside are taken back here too.
+    Effect::new(move |_| {
+        let plies = notation.read().len();
+        if plies >= history.read_untracked().len() {
+            return;
+        }
+        let position = history.read_untracked()[plies].clone();
+        history.write().truncate(plies);
+        undone.write().clear();
+        set_chess.set(position);
+    });
+
+    let square_at = move |x: f64, y: f64| {
+        let rect = board_ref.get_untracked()?.get_bounding_client_rect();
+        let column = ((x - rect.left()) / rect.width() * 8.0).floor();
+        let row = ((y - rect.top()) / rect.height() * 8.0).floor();
+        if !(0.0..8.0).contains(&column) || !(0.0..8.0).contains(&row) {
+            return None;
+        }
+        Some(from_screen(
+            column as u32,
+            row as u32,
+            bottom.get_untracked(),
+        ))
+    };
+
+    let on_pointer_down = move |square: Square, piece: Piece, ev: PointerEvent| {
+        if looking_back.get_untracked() {
+            return;
+        }
+        dropped_on.set(None);
+        set_selected_drop.set(None);
+        set_promoting.set(None);
+        let was_selected = selected_piece.get().map(|(s, _)| s) == Some(square);
+
+        if piece.color != current_color.get() {
+            set_selected_piece.set((!was_selected).then_some((square, piece)));
+            return;
+        }
+        set_selected_piece.set(Some((square, piece)));
+
+        let Some(board) = board_ref.get_untracked() else {
+            return;
+        };
+        ev.prevent_default();
+        if let Err(e) = board.set_pointer_capture(ev.pointer_id()) {
+            error!("could not capture the pointer: {e:?}");
+        }
+        drag.set(Some(Drag {
+            from: square,
+            piece,
+            x: ev.client_x() as f64,
+            y: ev.client_y() as f64,
+            size: board.get_bounding_client_rect().width() / 8.0,
+            moved: false,
+            was_selected,
+        }));
+    };
+
+    let on_pointer_move = move |ev: PointerEvent| {
+        drag.update(|drag| {
+            if let Some(drag) = drag {
+                drag.x = ev.client_x() as f64;
+                drag.y = ev.client_y() as f64;
+                drag.moved = true;
             }
+        });
+    };
+
+    let on_pointer_up = move |ev: PointerEvent| {
+        let Some(d) = drag.get() else {
+            return;
         };
+        drag.set(None);
 
-        view! { <Pieces board current_color on_click /> }
+        let target = square_at(ev.client_x() as f64, ev.client_y() as f64);
+        if target == Some(d.from) {
+            if d.was_selected {
+                set_selected_piece.set(None);
+            }
+            return;
+        }
+        // Dropped off the board or somewhere illegal, the piece just goes back.
+        if let Some(target) = target {
+            dropped_on.set(d.moved.then_some(target));
+            play_to(d.from, target);
+        }
     };
 
-    let move_indicators = move || {
-        log!("selected: {:?}", selected_piece.get());
+    // Enter on a square works like clicking it: pick up a piece, or put the picked up one there.
+    let confirm_square = move || {
+        let square = cursor.get_untracked();
+        if looking_back.get_untracked() {
+            return;
+        }
 
-        let (square, _piece) = match selected_piece.get() {
-            Some(s) => s,
-            None => return Either::Left(()),
+        if let Some(role) = selected_drop.get_untracked() {
+            let drop = Move::Put { role, to: square };
+            if chess.read_untracked().is_legal(drop) {
+                move_chess(drop);
+                return;
+            }
+        }
+        if let Some((from, _piece)) = selected_piece.get_untracked() {
+            if from != square && play_to(from, square) {
+                return;
+            }
+        }
+
+        set_selected_drop.set(None);
+        set_promoting.set(None);
+        let piece = chess.read_untracked().board().piece_at(square);
+        let was_selected = selected_piece.get_untracked().map(|(s, _)| s) == Some(square);
+        match piece {
+            Some(piece) if !was_selected => set_selected_piece.set(Some((square, piece))),
+            _ => set_selected_piece.set(None),
+        }
+    };
+
+    let on_key_down = move |ev: KeyboardEvent| {
+        let (column, row) = match ev.key().as_str() {
+            "ArrowLeft" => (-1, 0),
+            "ArrowRight" => (1, 0),
+            "ArrowUp" => (0, -1),
+            "ArrowDown" => (0, 1),
+            "Enter" | " " => {
+                ev.prevent_default();
+                confirm_square();
+                return;
+            }
+            "Escape" => {
+                set_selected_piece.set(None);
+                set_selected_drop.set(None);
+                return;
+            }
+            _ => return,
         };
+        ev.prevent_default();
 
-        log!("selected square: {square}");
+        let bottom = bottom.get_untracked();
+        let (x, y) = to_screen(cursor.get_untracked(), bottom);
+        let x = x.saturating_add_signed(column).min(7);
+        let y = y.saturating_add_signed(row).min(7);
+        cursor.set(from_screen(x, y, bottom));
+    };
+
+    let highlights = move || {
+        let mut squares = vec![];
+        if let Some(m) = last_move.get() {
+            squares.extend(m.from().map(|from| (from, "bg-highlight-last-move")));
+            squares.push((m.to(), "bg-highlight-last-move"));
+        }
+        if let Some((square, _piece)) = selected_piece.get() {
+            squares.push((square, "bg-highlight-selected"));
+        }
+        let pos = shown.read();
+        if pos.checkers().any() {
+            squares.extend(
+                pos.board()
+                    .king_of(pos.turn())
+                    .map(|king| (king, "bg-highlight-check")),
+            );
+        }
+
+        let bottom = bottom.get();
+        squares
+            .into_iter()
+            .map(|(square, class)| view! { <Highlight square class bottom /> })
+            .collect_view()
+    };
+
+    // Files along the bottom edge and ranks along the left one, whichever side is at the bottom.
+    let coordinate_labels = move || {
+        if !coordinates {
+            return None;
+        }
+        let bottom = bottom.get();
+        let labels = (0..8)
+            .map(|i| {
+                let file = from_screen(i, 7, bottom);
+                let rank = from_screen(0, i, bottom);
+                view! {
+                    <Coordinate square=file text=file.file().char() bottom along_bottom=true />
+                    <Coordinate square=rank text=rank.rank().char() bottom along_bottom=false />
+                }
+            })
+            .collect_view();
+        Some(labels)
+    };
+
+    let cursor_label = move || {
+        let square = cursor.get();
+        let piece = shown.read().board().piece_at(square);
+        let mut label = match piece {
+            Some(piece) => format!("{square}, {}", describe_piece(&piece)),
+            None => format!("{square}, empty"),
+        };
+        if selected_piece.get().map(|(s, _)| s) == Some(square) {
+            label.push_str(", selected");
+        }
+        label
+    };
+
+    let on_typed_move = move |ev: KeyboardEvent| {
+        if ev.key() != "Enter" {
+            return;
+        }
+        if looking_back.get_untracked() {
+            set_typed_error.set(Some("Go back to the newest position to move".to_string()));
+            return;
+        }
+        let m = parse_move(&chess.read_untracked(), &typed_move.get_untracked());
+        match m {
+            Ok(m) => {
+                move_chess(m);
+                set_typed_move.set(String::new());
+                set_typed_error.set(None);
+            }
+            Err(e) => set_typed_error.set(Some(e)),
+        }
+    };
+
+    let tracked = Memo::new(move |previous: Option<&Vec<TrackedPiece>>| {
+        track_pieces(previous.map_or(&[], Vec::as_slice), shown.read().board())
+    });
+    let dragging = Signal::derive(move || drag.read().filter(|d| d.moved).map(|d| d.from));
+
+    let dragged_piece = move || {
+        let d = drag.get().filter(|d| d.moved)?;
+        Some(view! {
+            <img
+                class="fixed z-50 pointer-events-none"
+                alt=""
+                style:left=format!("{}px", d.x - d.size / 2.0)
+                style:top=format!("{}px", d.y - d.size / 2.0)
+                style:width=format!("{}px", d.size)
+                style:height=format!("{}px", d.size)
+                src=piece_to_img(&d.piece)
+            />
+        })
+    };
+
+    let drop_indicators = move || {
+        let role = selected_drop.get()?;
+        let bottom = bottom.get();
 
         let indicators = chess
             .get()
             .legal_moves()
             .into_iter()
-            .filter(|m| {
-                // log!("legal move {m:?}");
-                match m {
-                    Move::Normal {
-                        from, promotion, ..
-                    } => *from == square && promotion.is_none(),
-                    Move::EnPassant { from, .. } => *from == square,
-                    Move::Castle { king, .. } => *king == square,
-                    Move::Put { .. } => false,
-                }
+            .filter_map(|m| match m {
+                Move::Put { role: r, to } if r == role => Some((m, to)),
+                _ => None,
             })
-            .map(|m| {
-                let s = match m {
-                    Move::Normal { to, .. } | Move::EnPassant { to, .. } => to,
+            .map(|(m, to)| view! { <Indicator square=to bottom on:click=move |_| move_chess(m) /> })
+            .collect_view();
 
-                    Move::Castle { rook, .. } => rook,
+        Some(indicators)
+    };
 
-                    _ => return Either::Left(()),
-                };
+    let pocket = move |color: Color| {
+        let on_click = move |role: Role| {
+            if color != current_color.get() || looking_back.get_untracked() {
+                return;
+            }
+            set_selected_piece.set(None);
+            set_selected_drop.update(|selected| {
+                *selected = match *selected == Some(role) {
+                    true => None,
+                    false => Some(role),
+                }
+            });
+        };
 
-                // log!("sq: {s:?}");
+        move || {
+            let pockets = shown.read().pockets().cloned()?;
+            Some(
+                view! { <Pocket color pieces=*pockets.get(color) selected=selected_drop on_click /> },
+            )
+        }
+    };
 
+    let move_indicators = move || {
+        log!("selected: {:?}", selected_piece.get());
+
+        let (square, _piece) = match selected_piece.get() {
+            Some(s) => s,
+            None => return Either::Left(()),
+        };
+
+        log!("selected square: {square}");
+        let bottom = bottom.get();
+
+        let indicators = move_targets(&chess.read(), square)
+            .into_iter()
+            .map(|(s, m)| {
                 let on_click = move |_| {
                     move_chess(m);
                 };
 
-                Either::Right(view! { <Indicator square=s on:click=on_click /> })
+                view! { <Indicator square=s bottom on:click=on_click /> }
             })
             .collect_view();
 
@@ -114,17 +528,11 @@ pub fn ChessBoard(
             .into_iter()
             .filter(|m| match m {
                 Move::Normal {
-                    role,
-                    from,
-                    capture,
-                    to,
-                    promotion,
+                    from, promotion, ..
                 } => *from == square && promotion.expect("lol, this cant happen") == Role::Knight,
                 _ => false,
             })
             .map(|m| {
-                let (visible, set_visible) = signal(false);
-
                 let s = match m {
                     Move::Normal { to, .. } => to,
                     _ => return Either::Left(()),
@@ -155,7 +563,7 @@ pub fn ChessBoard(
                     })
                     .collect::<Vec<_>>();
 
-                let on_click = move |_| set_visible.set(true);
+                let on_click = move |_| set_promoting.set(Some(s));
 
                 let on_selected = move |r: Role| {
                     if let Move::Normal {
@@ -177,19 +585,18 @@ pub fn ChessBoard(
                 };
 
                 Either::Right(view! {
-                    <Indicator square=s on:click=on_click />
+                    <Indicator square=s bottom on:click=on_click />
                     <ChooseRole
                         position=m.to()
                         pieces
+                        bottom
                         on_selected
                         style:display=move || {
-                            match visible.get() {
+                            match promoting.get() == Some(s) {
                                 true => "",
                                 false => "none",
                             }
                         }
-                        {..}
-                        class:rotate-180=move || current_color.read().is_white()
                     />
                 })
             })
@@ -202,52 +609,390 @@ pub fn ChessBoard(
     };
 
     view! {
-        <div
-            class="grid grid-cols-8 bg-contain rounded-md transition-transform duration-300 ease-in-out bg-[url(/board.png)] w-180 h-180 grid-rows-8"
-            class:rotate-180=move || current_color.read().is_white()
-        >
-            {move_indicators}
-            {pieces}
+        <div class="flex flex-col gap-2 items-center w-full">
+            // Each pocket sits on the side of its own pieces.
+            {move || pocket(!bottom.get())}
+            <div
+                class="grid relative grid-cols-8 w-full bg-contain rounded-md outline-none touch-none bg-[url(/board.png)] aspect-square max-w-180 grid-rows-8"
+                node_ref=board_ref
+                tabindex="0"
+                role="application"
+                aria-label="Chess board. Move between squares with the arrow keys, pick up and put down pieces with Enter."
+                aria-activedescendant=cursor_id
+                on:pointermove=on_pointer_move
+                on:pointerup=on_pointer_up
+                on:pointercancel=move |_| drag.set(None)
+                on:keydown=on_key_down
+                on:focus=move |_| set_focused.set(true)
+                on:blur=move |_| set_focused.set(false)
+            >
+                <div
+                    id=cursor_id.clone()
+                    role="img"
+                    aria-label=cursor_label
+                    class="z-30 rounded-sm pointer-events-none ring-4 ring-sky-400"
+                    class:hidden=move || !focused.get()
+                    style:grid-column=move || grid_cell(cursor.get(), bottom.get()).0
+                    style:grid-row=move || grid_cell(cursor.get(), bottom.get()).1
+                />
+                {highlights}
+                {coordinate_labels}
+                {move_indicators}
+                {drop_indicators}
+                <Pieces
+                    pieces=tracked
+                    bottom
+                    dragging
+                    snap=dropped_on
+                    on_pointer_down=Callback::new(move |(square, piece, ev)| {
+                        on_pointer_down(square, piece, ev)
+                    })
+                />
 
+            </div>
+            {dragged_piece}
+            {move || pocket(bottom.get())}
+            <div class="flex flex-row flex-wrap gap-2 justify-center">
+                <input
+                    type="text"
+                    class="w-40"
+                    placeholder="Nf3 or g1f3"
+                    aria-label="Type a move"
+                    prop:value=typed_move
+                    on:input=move |ev| set_typed_move.set(event_target_value(&ev))
+                    on:keydown=on_typed_move
+                />
+                <button
+                    on:click=move |_| undo()
+                    class="p-2 text-lg button-secondary"
+                    disabled=move || history.read().is_empty()
+                >
+                    "Undo"
+                </button>
+                <button
+                    on:click=move |_| redo()
+                    class="p-2 text-lg button-secondary"
+                    disabled=move || undone.read().is_empty()
+                >
+                    "Redo"
+                </button>
+                <button
+                    on:click=move |_| set_flipped.update(|flipped| *flipped = !*flipped)
+                    class="p-2 text-lg button-secondary"
+                    aria-pressed=move || flipped.get().to_string()
+                >
+                    "Flip board"
+                </button>
+                <label class="flex gap-1 items-center font-sans">
+                    <input
+                        type="checkbox"
+                        prop:checked=move || orientation.get() == Orientation::SideToMove
+                        on:change=move |ev| {
+                            let follow = event_target_checked(&ev);
+                            // Stop turning with the side that is at the bottom right now.
+                            orientation.set(match follow {
+                                true => Orientation::SideToMove,
+                                false => bottom.get_untracked().into(),
+                            });
+                            set_flipped.set(false);
+                        }
+                    />
+                    "Turn with each move"
+                </label>
+            </div>
+            <span class="text-red-500" role="alert">
+                {move || typed_error.get()}
+            </span>
+            <div class="sr-only" aria-live="polite">
+                {announcement}
+            </div>
         </div>
     }
 }
 
+/// The grid column and row a square is drawn in, counted from 0 at the top left,
+/// with the pieces of `bottom` starting at the bottom edge.
+fn to_screen(square: Square, bottom: Color) -> (u32, u32) {
+    let (file, rank) = (square.file().to_u32(), square.rank().to_u32());
+    match bottom {
+        Color::White => (file, 7 - rank),
+        Color::Black => (7 - file, rank),
+    }
+}
+
+fn from_screen(column: u32, row: u32, bottom: Color) -> Square {
+    let (file, rank) = match bottom {
+        Color::White => (column, 7 - row),
+        Color::Black => (7 - column, row),
+    };
+    Square::from_coords(File::new(file), Rank::new(rank))
+}
+
+/// The CSS `grid-column` and `grid-row` of a square, which count from 1.
+fn grid_cell(square: Square, bottom: Color) -> (String, String) {
+    let (column, row) = to_screen(square, bottom);
+    ((column + 1).to_string(), (row + 1).to_string())
+}
+
+/// Gives the pieces of `board` the ids of the `previous` pieces they most likely are.
+/// Pieces that stayed keep theirs, moved pieces take the id of the nearest vanished piece of the
+/// same kind, and a pawn and the piece it promoted to share one. Anything else is new.
+pub fn track_pieces(previous: &[TrackedPiece], board: &Board) -> Vec<TrackedPiece> {
+    let mut next_id = previous.iter().map(|t| t.id + 1).max().unwrap_or(0);
+
+    let (stayed, mut vanished): (Vec<_>, Vec<_>) = previous
+        .iter()
+        .partition(|t| board.piece_at(t.square) == Some(t.piece));
+    let mut tracked = stayed.into_iter().copied().collect::<Vec<_>>();
+
+    let mut appeared = board
+        .clone()
+        .into_iter()
+        .filter(|(square, _)| !tracked.iter().any(|t| t.square == *square))
+        .collect::<Vec<_>>();
+
+    let same_kind = |a: &Piece, b: &Piece| a == b;
+    let promotion =
+        |a: &Piece, b: &Piece| a.color == b.color && (a.role == Role::Pawn || b.role == Role::Pawn);
+    for matches in [&same_kind as &dyn Fn(&Piece, &Piece) -> bool, &promotion] {
+        appeared.retain(|&(square, piece)| {
+            let nearest = vanished
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| matches(&t.piece, &piece))
+                .min_by_key(|(_, t)| t.square.distance(square))
+                .map(|(i, _)| i);
+            match nearest {
+                Some(i) => {
+                    let id = vanished.swap_remove(i).id;
+                    tracked.push(TrackedPiece { id, piece, square });
+                    false
+                }
+                None => true,
+            }
+        });
+    }
+
+    for (square, piece) in appeared {
+        tracked.push(TrackedPiece {
+            id: next_id,
+            piece,
+            square,
+        });
+        next_id += 1;
+    }
+
+    tracked.sort_by_key(|t| t.id);
+    tracked
+}
+
+/// Every square the piece on `from` can be put on, with the move that puts it there.
+/// Castling is offered on the rook and on the square the king ends up on. In Chess960 that square
+/// can be the king's own, or where another king move already goes, then only the rook castles.
+fn move_targets(pos: &VariantPosition, from: Square) -> Vec<(Square, Move)> {
+    let moves = pos.legal_moves();
+
+    let mut targets = moves
+        .iter()
+        .filter_map(|m| match *m {
+            Move::Normal {
+                from: f,
+                to,
+                promotion: None,
+                ..
+            } if f == from => Some((to, *m)),
+            Move::EnPassant { from: f, to } if f == from => Some((to, *m)),
+            Move::Castle { king, rook } if king == from => Some((rook, *m)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    for m in &moves {
+        let Some(side) = m.castling_side().filter(|_| m.from() == Some(from)) else {
+            continue;
+        };
+        let king_to = side.king_to(pos.turn());
+        if king_to != from && !targets.iter().any(|(to, _)| *to == king_to) {
+            targets.push((king_to, *m));
+        }
+    }
+
+    targets
+}
+
+fn move_to(pos: &VariantPosition, from: Square, to: Square) -> Option<Move> {
+    move_targets(pos, from)
+        .into_iter()
+        .find(|(target, _)| *target == to)
+        .map(|(_, m)| m)
+}
+
+/// Reads a typed move, in SAN like `Nf3` or UCI like `g1f3`.
+fn parse_move(pos: &VariantPosition, text: &str) -> Result<Move, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Type a move first".to_string());
+    }
+
+    if let Ok(san) = SanPlus::from_ascii(text.as_bytes()) {
+        match san.san.to_move(pos) {
+            Ok(m) => return Ok(m),
+            Err(SanError::AmbiguousSan) => {
+                return Err(format!("{text} could be more than one move"))
+            }
+            Err(SanError::IllegalSan) => {}
+        }
+    }
+    if let Ok(uci) = UciMove::from_ascii(text.as_bytes()) {
+        if let Ok(m) = uci.to_move(pos) {
+            return Ok(m);
+        }
+    }
+
+    Err(format!("{text} can't be played here"))
+}
+
+/// What a screen reader says after a move, like "White played Nf3, check".
+fn describe_move(after: &VariantPosition, san: San) -> String {
+    let mover = match after.turn() {
+        Color::White => "Black",
+        Color::Black => "White",
+    };
+    let mut text = format!("{mover} played {san}");
+    if after.is_checkmate() {
+        text.push_str(", checkmate");
+    } else if after.is_check() {
+        text.push_str(", check");
+    }
+    text
+}
+
+/// A piece in words, like "white knight".
+pub fn describe_piece(piece: &Piece) -> String {
+    let color = match piece.color {
+        Color::White => "white",
+        Color::Black => "black",
+    };
+    let role = match piece.role {
+        Role::Pawn => "pawn",
+        Role::Knight => "knight",
+        Role::Bishop => "bishop",
+        Role::Rook => "rook",
+        Role::Queen => "queen",
+        Role::King => "king",
+    };
+    format!("{color} {role}")
+}
+
+/// The captured pieces a side can drop back on the board.
 #[component]
-fn Pieces(
-    board: Board,
-    current_color: Signal<Color>,
-    on_click: impl Fn(Square, Piece) + 'static + std::marker::Send + std::marker::Sync,
+fn Pocket(
+    color: Color,
+    pieces: ByRole<u8>,
+    selected: ReadSignal<Option<Role>>,
+    on_click: impl Fn(Role) + 'static + std::marker::Send + std::marker::Sync,
 ) -> impl IntoView {
-    let on_click = move |(square, piece)| on_click(square, piece);
     let on_click = Callback::new(on_click);
 
-    board
+    let pieces = pieces
+        .zip_role()
         .into_iter()
-        .map(|(square, piece)| {
-            let on_click = move |_| {
-                on_click.run((square, piece));
-            };
-
+        .filter(|(_role, count)| *count > 0)
+        .map(|(role, count)| {
+            let piece = Piece { color, role };
             view! {
-                <ChessPiece
-                    piece
-                    position=square
-                    on:click=on_click
-                    class:rotate-180=move || { current_color.read().is_white() }
-                />
+                <button
+                    on:click=move |_| on_click.run(role)
+                    class="flex relative flex-row items-end p-1 rounded-md"
+                    class:bg-zinc-700=move || selected.get() == Some(role)
+                >
+                    <img class="w-16 h-16" alt=describe_piece(&piece) src=piece_to_img(&piece) />
+                    <span class="text-lg">{count}</span>
+                </button>
             }
         })
-        .collect_view()
+        .collect_view();
+
+    view! { <div class="flex flex-row gap-2 h-12 sm:h-20">{pieces}</div> }
 }
 
+/// The pieces on a board grid, see [`track_pieces`] for how they keep their views between moves.
 #[component]
-fn Indicator(square: Square) -> impl IntoView {
-    let file = square.file().to_u32() + 1;
-    let rank = square.rank().to_u32() + 1;
+pub fn Pieces(
+    pieces: Memo<Vec<TrackedPiece>>,
+    #[prop(into)] bottom: Signal<Color>,
+    /// The square of the piece that is being dragged, it stays behind faded out.
+    #[prop(optional, into)]
+    dragging: MaybeProp<Square>,
+    /// A piece that arrives on this square jumps there instead of sliding.
+    #[prop(optional, into)]
+    snap: MaybeProp<Square>,
+    /// Without it the pieces can't be picked up.
+    #[prop(optional)]
+    on_pointer_down: Option<Callback<(Square, Piece, PointerEvent)>>,
+) -> impl IntoView {
+    // Keyed by id, so a move only touches the pieces that moved, were captured or promoted.
     view! {
-        <div class="p-4" style:grid-column=file.to_string() style:grid-row=rank.to_string()>
-            <div class="relative z-40 w-full h-full rounded-full rotate-180 bg-zinc-700/50" />
+        <For each=move || pieces.get() key=|tracked| tracked.id let:tracked>
+            {
+                let id = tracked.id;
+                let current = Memo::new(move |_| {
+                    pieces.read().iter().find(|t| t.id == id).copied().unwrap_or(tracked)
+                });
+                let on_pointer_down = move |ev| {
+                    let TrackedPiece { square, piece, .. } = current.get_untracked();
+                    if let Some(on_pointer_down) = on_pointer_down {
+                        on_pointer_down.run((square, piece, ev));
+                    }
+                };
+
+                view! {
+                    <ChessPiece
+                        piece=Signal::derive(move || current.get().piece)
+                        position=Signal::derive(move || Some(current.get().square))
+                        bottom
+                        on:pointerdown=on_pointer_down
+                        class:opacity-30=move || dragging.get() == Some(current.get().square)
+                        class:transition-none=move || snap.get() == Some(current.get().square)
+                    />
+                }
+            }
+        </For>
+    }
+}
+
+/// Colours a square, the class decides what for.
+#[component]
+pub fn Highlight(square: Square, class: &'static str, bottom: Color) -> impl IntoView {
+    let (column, row) = grid_cell(square, bottom);
+    view! {
+        <div class=format!("pointer-events-none {class}") style:grid-column=column style:grid-row=row />
+    }
+}
+
+/// A file letter in the bottom right corner of its square, or a rank number in the top left.
+#[component]
+fn Coordinate(square: Square, text: char, bottom: Color, along_bottom: bool) -> impl IntoView {
+    let (column, row) = grid_cell(square, bottom);
+    view! {
+        <div
+            class="flex p-1 font-sans text-sm font-bold pointer-events-none select-none text-coordinate"
+            class:items-end=along_bottom
+            class:justify-end=along_bottom
+            style:grid-column=column
+            style:grid-row=row
+        >
+            {text.to_string()}
+        </div>
+    }
+}
+
+#[component]
+fn Indicator(square: Square, bottom: Color) -> impl IntoView {
+    let (column, row) = grid_cell(square, bottom);
+    view! {
+        <div class="p-4" style:grid-column=column style:grid-row=row data-square=square.to_string()>
+            <div class="relative z-40 w-full h-full rounded-full bg-zinc-700/50" />
         </div>
     }
 }
@@ -256,12 +1001,13 @@ fn Indicator(square: Square) -> impl IntoView {
 fn ChooseRole(
     position: Square,
     pieces: Vec<Piece>,
+    bottom: Color,
     on_selected: impl Fn(Role) + 'static + std::marker::Sync + std::marker::Send,
 ) -> impl IntoView {
-    let file = position.file().to_u32() + 1;
-    let rank = position.rank().to_u32() + 1;
+    let (column, row) = to_screen(position, bottom);
 
-    let file_start = file as f32 - pieces.len() as f32 / 2.0;
+    // Centred on the promotion square, the grid counts from 1.
+    let file_start = column as f32 + 1.0 - pieces.len() as f32 / 2.0;
 
     let on_selected = Callback::new(on_selected);
 
@@ -269,7 +1015,11 @@ fn ChooseRole(
         .into_iter()
         .map(|piece| {
             let on_click = move |_| on_selected.run(piece.role);
-            view! { <ChessPiece piece class:z-50=true on:click=on_click /> }
+            view! {
+                <button on:click=on_click aria-label=format!("Promote to {}", describe_piece(&piece))>
+                    <ChessPiece piece class:z-50=true />
+                </button>
+            }
         })
         .collect_view();
 
@@ -279,27 +1029,40 @@ fn ChooseRole(
             class="flex z-50 justify-between content-center p-1 bg-white rounded-md"
             style:grid-column-start=file_start.to_string()
             style:grid-column-end=end
-            style:grid-row=rank.to_string()
+            style:grid-row=(row + 1).to_string()
         >
             {pieces}
         </div>
     }
 }
 
+/// A piece on its square of the board, or on its own without a `position`.
+/// On the board it is placed with a transform, so moving it to another square slides it there.
 #[component]
-fn ChessPiece(piece: Piece, #[prop(optional)] position: Option<Square>) -> impl IntoView {
+pub fn ChessPiece(
+    #[prop(into)] piece: Signal<Piece>,
+    #[prop(optional, into)] position: MaybeProp<Square>,
+    /// The side at the bottom of the board, it decides where `position` is drawn.
+    #[prop(into, default = Color::White.into())]
+    bottom: Signal<Color>,
+) -> impl IntoView {
+    let alt = move || match position.get() {
+        Some(square) => format!("{} on {square}", describe_piece(&piece.get())),
+        None => describe_piece(&piece.get()),
+    };
+    // The piece is an eighth of the board, so each square across is another 100%.
+    let transform = move || {
+        let (column, row) = to_screen(position.get()?, bottom.get());
+        Some(format!("translate({}%, {}%)", column * 100, row * 100))
+    };
+
     view! {
         <img
-            class="transition-transform duration-300 ease-in-out rotate-180"
-            style:grid-column=position
-                .map(|p| p.file().to_u32() + 1)
-                .map(|f| f.to_string())
-                .unwrap_or_default()
-            style:grid-row=position
-                .map(|p| p.rank().to_u32() + 1)
-                .map(|r| r.to_string())
-                .unwrap_or_default()
-            src=piece_to_img(&piece)
+            alt=alt
+            class="transition-transform duration-300 ease-in-out"
+            class=(["absolute", "top-0", "left-0", "w-1/8", "h-1/8"], move || position.get().is_some())
+            style:transform=transform
+            src=move || piece_to_img(&piece.get())
         />
     }
 }
@@ -323,3 +1086,193 @@ pub fn piece_to_img(piece: &Piece) -> &'static str {
         (Color::White, Role::King) => "/pieces/wk.png",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shakmaty::variant::Variant;
+
+    fn position(fen: &str, mode: CastlingMode) -> VariantPosition {
+        let fen = Fen::from_ascii(fen.as_bytes()).expect("valid fen");
+        VariantPosition::from_setup(Variant::Chess, fen.into_setup(), mode).expect("legal position")
+    }
+
+    fn castles(pos: &VariantPosition, from: Square, to: Square, king: Square, rook: Square) {
+        assert_eq!(
+            move_to(pos, from, to),
+            Some(Move::Castle { king, rook }),
+            "{from} to {to}"
+        );
+    }
+
+    fn targets_on(pos: &VariantPosition, from: Square, to: Square) -> usize {
+        move_targets(pos, from)
+            .iter()
+            .filter(|(target, _)| *target == to)
+            .count()
+    }
+
+    #[test]
+    fn standard_castling_by_king_destination_or_rook() {
+        let white = position(
+            "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+            CastlingMode::Standard,
+        );
+        castles(&white, Square::E1, Square::G1, Square::E1, Square::H1);
+        castles(&white, Square::E1, Square::H1, Square::E1, Square::H1);
+        castles(&white, Square::E1, Square::C1, Square::E1, Square::A1);
+        castles(&white, Square::E1, Square::A1, Square::E1, Square::A1);
+
+        let black = position(
+            "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1",
+            CastlingMode::Standard,
+        );
+        castles(&black, Square::E8, Square::G8, Square::E8, Square::H8);
+        castles(&black, Square::E8, Square::H8, Square::E8, Square::H8);
+        castles(&black, Square::E8, Square::C8, Square::E8, Square::A8);
+        castles(&black, Square::E8, Square::A8, Square::E8, Square::A8);
+    }
+
+    #[test]
+    fn castling_from_the_start_position() {
+        let start = position(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            CastlingMode::Standard,
+        );
+        let mut pos = start;
+        for uci in ["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6"] {
+            let m = UciMove::from_ascii(uci.as_bytes())
+                .expect("valid uci")
+                .to_move(&pos)
+                .expect("legal move");
+            pos = pos.play(m).expect("legal move");
+        }
+        castles(&pos, Square::E1, Square::G1, Square::E1, Square::H1);
+        castles(&pos, Square::E1, Square::H1, Square::E1, Square::H1);
+    }
+
+    #[test]
+    fn no_castling_through_check() {
+        let pos = position(
+            "r3k2r/8/8/8/8/8/5r2/R3K2R w KQkq - 0 1",
+            CastlingMode::Standard,
+        );
+        assert_eq!(move_to(&pos, Square::E1, Square::G1), None);
+        assert_eq!(move_to(&pos, Square::E1, Square::H1), None);
+    }
+
+    #[test]
+    fn chess960_rook_on_king_destination() {
+        // The rook stands on g1 where the king goes, so both ways are one target.
+        let pos = position("k7/8/8/8/8/8/8/5KR1 w G - 0 1", CastlingMode::Chess960);
+        castles(&pos, Square::F1, Square::G1, Square::F1, Square::G1);
+        assert_eq!(targets_on(&pos, Square::F1, Square::G1), 1);
+
+        let pos = position("k7/8/8/8/8/8/8/2RK4 w C - 0 1", CastlingMode::Chess960);
+        castles(&pos, Square::D1, Square::C1, Square::D1, Square::C1);
+        assert_eq!(targets_on(&pos, Square::D1, Square::C1), 1);
+    }
+
+    #[test]
+    fn chess960_king_already_on_destination() {
+        // The king stays on g1 and only the rook moves, clicking the king again deselects it.
+        let pos = position("k7/8/8/8/8/8/8/6KR w H - 0 1", CastlingMode::Chess960);
+        castles(&pos, Square::G1, Square::H1, Square::G1, Square::H1);
+        assert_eq!(targets_on(&pos, Square::G1, Square::G1), 0);
+    }
+
+    #[test]
+    fn chess960_king_destination_taken_by_a_normal_move() {
+        // Kc1 is a normal king move too, so c1 stays that and the rook square castles.
+        let pos = position("7k/8/8/8/8/8/8/RK6 w A - 0 1", CastlingMode::Chess960);
+        assert!(matches!(
+            move_to(&pos, Square::B1, Square::C1),
+            Some(Move::Normal {
+                role: Role::King,
+                ..
+            })
+        ));
+        castles(&pos, Square::B1, Square::A1, Square::B1, Square::A1);
+    }
+
+    #[test]
+    fn chess960_rook_next_to_king() {
+        // The rook ends up where it started, the king jumps over it.
+        let pos = position("k7/8/8/8/8/8/8/4KR2 w F - 0 1", CastlingMode::Chess960);
+        castles(&pos, Square::E1, Square::F1, Square::E1, Square::F1);
+        castles(&pos, Square::E1, Square::G1, Square::E1, Square::F1);
+    }
+
+    #[test]
+    fn chess960_castling_both_sides() {
+        let pos = position(
+            "qrkbbnrn/pppppppp/8/8/8/8/PPPPPPPP/QRKBBNRN w GBgb - 0 1",
+            CastlingMode::Chess960,
+        );
+        assert!(move_targets(&pos, Square::C1).is_empty());
+
+        let pos = position(
+            "1rk4r/8/8/8/8/8/8/1RK3R1 w GBhb - 0 1",
+            CastlingMode::Chess960,
+        );
+        castles(&pos, Square::C1, Square::G1, Square::C1, Square::G1);
+        castles(&pos, Square::C1, Square::B1, Square::C1, Square::B1);
+        // c1 is the king's own square on the queen side.
+        assert_eq!(targets_on(&pos, Square::C1, Square::C1), 0);
+    }
+
+    #[test]
+    fn orientation_puts_own_pieces_at_the_bottom() {
+        assert_eq!(to_screen(Square::A1, Color::White), (0, 7));
+        assert_eq!(to_screen(Square::H8, Color::White), (7, 0));
+        assert_eq!(to_screen(Square::A1, Color::Black), (7, 0));
+        assert_eq!(to_screen(Square::H8, Color::Black), (0, 7));
+
+        for square in Square::ALL {
+            for bottom in Color::ALL {
+                let (column, row) = to_screen(square, bottom);
+                assert_eq!(from_screen(column, row, bottom), square);
+            }
+        }
+    }
+
+    fn track_after(fen: &str, moves: &[&str]) -> (Vec<TrackedPiece>, Vec<TrackedPiece>) {
+        let mut pos = position(fen, CastlingMode::Standard);
+        let before = track_pieces(&[], pos.board());
+        for m in moves {
+            let m = SanPlus::from_ascii(m.as_bytes()).expect("valid san");
+            let m = m.san.to_move(&pos).expect("legal move");
+            pos = pos.play(m).expect("legal move");
+        }
+        let after = track_pieces(&before, pos.board());
+        (before, after)
+    }
+
+    fn id_on(tracked: &[TrackedPiece], square: Square) -> Option<u32> {
+        tracked.iter().find(|t| t.square == square).map(|t| t.id)
+    }
+
+    #[test]
+    fn moved_pieces_keep_their_id() {
+        let (before, after) = track_after(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            &["e4", "d5", "exd5"],
+        );
+        assert_eq!(after.len(), 31);
+        assert_eq!(id_on(&before, Square::E2), id_on(&after, Square::D5));
+        assert_eq!(id_on(&before, Square::A1), id_on(&after, Square::A1));
+        // The captured pawn is gone, no other piece took its id.
+        let captured = id_on(&before, Square::D7);
+        assert!(after.iter().all(|t| Some(t.id) != captured));
+    }
+
+    #[test]
+    fn castling_and_promotion_keep_their_ids() {
+        let (before, after) =
+            track_after("8/1P6/8/k7/8/8/8/4K2R w K - 0 1", &["O-O", "Kb4", "b8=Q"]);
+        assert_eq!(id_on(&before, Square::E1), id_on(&after, Square::G1));
+        assert_eq!(id_on(&before, Square::H1), id_on(&after, Square::F1));
+        assert_eq!(id_on(&before, Square::B7), id_on(&after, Square::B8));
+        assert_eq!(after.len(), before.len());
+    }
+}
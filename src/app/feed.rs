@@ -1,14 +1,11 @@
-use std::f32::consts::E;
 use std::ops::Range;
 
-use chrono::{DateTime, Utc};
 use leptos::either::Either;
-use leptos::logging::*;
 use leptos::prelude::*;
 use shakmaty::Piece;
 
 use crate::app::chess::piece_to_img;
-use crate::types::{Error, Pieces, Vote};
+use crate::types::{Error, Vote};
 
 const PAGE_SIZE: i64 = 20;
 
@@ -49,7 +46,7 @@ pub fn FeedPage() -> impl IntoView {
 #[component]
 fn VoteComponent(vote: Vote) -> impl IntoView {
     view! {
-        <div class="flex flex-row justify-between items-center p-4 w-full h-fit rounded-4xl border-[#ffffff1a]">
+        <div class="flex flex-col gap-4 justify-between items-center p-4 w-full md:flex-row h-fit rounded-4xl border-[#ffffff1a]">
             <div class="flex flex-row gap-4 justify-center items-center p-4 rounded-2xl w-fit h-fit bg-secondary">
                 <VotePiece piece=vote.first_piece.into() voted_for=vote.voted_for_first />
                 <div class="z-40 p-4 -m-9 bg-white rounded-full rotate-12 w-fit h-fit text-background">
@@ -81,7 +78,7 @@ pub fn VotePiece(
     let img_src = piece_to_img(&piece);
     view! {
         <img
-            class="w-80 h-80 rounded-lg border-2 bg-secondary border-[#ffffff1a]"
+            class="w-40 h-40 rounded-lg sm:w-80 sm:h-80 border-2 bg-secondary border-[#ffffff1a]"
             class:bg-secondary-hover=move || voted_for.get().unwrap_or(false)
             class:hover:bg-secondary-hover=hoverable.unwrap_or(false)
             src=img_src
@@ -92,16 +89,16 @@ pub fn VotePiece(
 
 #[server]
 async fn fetch_feed(range: Range<i64>) -> Result<Vec<Vote>, Error> {
-    use crate::types::AppState;
+    use crate::types::{AppState, Pieces};
     let app_state = expect_context::<AppState>();
 
     let mut transaction = app_state.db.pool.begin().await?;
 
     let votes = sqlx::query_as!(Vote,
     r#"
-    SELECT votes.id, users.username, votes.first_piece as "first_piece: Pieces", votes.second_piece as "second_piece: Pieces", votes.voted_for_first, votes.reason, votes.created_at
+    SELECT votes.id, COALESCE(users.username, 'Deleted user') as "username!", votes.first_piece as "first_piece: Pieces", votes.second_piece as "second_piece: Pieces", votes.voted_for_first, votes.reason, votes.created_at
     FROM votes
-    JOIN users ON votes.user_id = users.id
+    LEFT JOIN users ON votes.user_id = users.id
     ORDER BY votes.created_at DESC
     LIMIT $1 OFFSET $2
     "#,
@@ -1,4 +1,4 @@
-use leptos::{children, either::Either, ev::MouseEvent, prelude::*};
+use leptos::{either::Either, ev::MouseEvent, prelude::*};
 use shakmaty::{Color, KnownOutcome};
 
 #[component]
@@ -0,0 +1,252 @@
+use std::time::Duration;
+
+use leptos::either::Either;
+use leptos::prelude::*;
+use leptos_router::hooks::use_query_map;
+use shakmaty::fen::*;
+use shakmaty::san::*;
+use shakmaty::variant::VariantPosition;
+use shakmaty::*;
+
+use crate::app::chess::{track_pieces, Highlight, Orientation, Pieces, TrackedPiece};
+use crate::app::move_list::MoveList;
+use crate::types::pgn::parse_pgn;
+use crate::types::start::StartPosition;
+use crate::types::Error;
+
+/// How long each move stays on the board while playing, in milliseconds.
+const SPEEDS: [(&str, u64); 4] = [
+    ("quarter second", 250),
+    ("half second", 500),
+    ("second", 1000),
+    ("two seconds", 2000),
+];
+const DEFAULT_SPEED: u64 = 1000;
+
+/// A game to show in a [`GameViewer`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Game {
+    Pgn(String),
+    Moves(Vec<(San, Fen)>),
+}
+
+impl From<Vec<(San, Fen)>> for Game {
+    fn from(moves: Vec<(San, Fen)>) -> Self {
+        Game::Moves(moves)
+    }
+}
+
+/// Plays back a finished game, for games that are not a password. It only ever shows positions,
+/// nothing on it can be moved.
+#[component]
+pub fn GameViewer(
+    #[prop(into)] game: Game,
+    /// The variant and position the game starts from, normal chess if not set.
+    #[prop(optional)]
+    start: StartPosition,
+    /// The side at the bottom, the flip button turns it around from there.
+    #[prop(optional)]
+    orientation: Orientation,
+) -> impl IntoView {
+    let positions = match replay(&start, &game) {
+        Ok(positions) => positions,
+        Err(e) => {
+            return Either::Left(view! {
+                <div class="text-red-700">"Could not read the game: " {e.to_string()}</div>
+            });
+        }
+    };
+    let notation = positions
+        .iter()
+        .skip(1)
+        .map(|(san, fen, _pos)| {
+            (
+                san.expect("every move after the start has one"),
+                fen.clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let positions = StoredValue::new(
+        positions
+            .into_iter()
+            .map(|(_san, _fen, pos)| pos)
+            .collect::<Vec<_>>(),
+    );
+    let len = notation.len();
+    let notation = Signal::stored(notation);
+
+    // From the start, `None` is the last position like everywhere else a `MoveList` is used.
+    let viewing = RwSignal::new((len > 0).then_some(0));
+    let plies = move || viewing.get().unwrap_or(len);
+    let (playing, set_playing) = signal(false);
+    let (speed, set_speed) = signal(DEFAULT_SPEED);
+    let (flipped, set_flipped) = signal(false);
+
+    let bottom = Signal::derive(move || {
+        let color = match orientation {
+            Orientation::White => Color::White,
+            Orientation::Black => Color::Black,
+            Orientation::SideToMove => positions.with_value(|p| p[plies()].turn()),
+        };
+        match flipped.get() {
+            true => !color,
+            false => color,
+        }
+    });
+
+    let tracked = Memo::new(move |previous: Option<&Vec<TrackedPiece>>| {
+        positions
+            .with_value(|p| track_pieces(previous.map_or(&[], Vec::as_slice), p[plies()].board()))
+    });
+
+    let last_move = move || {
+        let ply = plies().checked_sub(1)?;
+        let san = notation.read().get(ply)?.0;
+        positions.with_value(|p| san.to_move(&p[ply]).ok())
+    };
+
+    let step = move || {
+        let next = plies() + 1;
+        if next >= len {
+            set_playing.set(false);
+        }
+        viewing.set((next < len).then_some(next));
+    };
+
+    // One interval while playing, it is replaced when the speed changes.
+    let interval = StoredValue::new(None::<IntervalHandle>);
+    let stop = move || {
+        if let Some(handle) = interval.get_value() {
+            handle.clear();
+        }
+        interval.set_value(None);
+    };
+    Effect::new(move |_| {
+        stop();
+        if !playing.get() {
+            return;
+        }
+        match set_interval_with_handle(step, Duration::from_millis(speed.get())) {
+            Ok(handle) => interval.set_value(Some(handle)),
+            Err(e) => leptos::logging::error!("could not start playing: {e:?}"),
+        }
+    });
+    on_cleanup(stop);
+
+    let on_play = move |_| {
+        // Playing again from the end starts over.
+        if !playing.get_untracked() && viewing.get_untracked().is_none() {
+            viewing.set(Some(0));
+        }
+        set_playing.update(|playing| *playing = !*playing);
+    };
+
+    let highlights = move || {
+        let m = last_move()?;
+        let bottom = bottom.get();
+        Some(view! {
+            {m.from().map(|from| view! { <Highlight square=from class="bg-highlight-last-move" bottom /> })}
+            <Highlight square=m.to() class="bg-highlight-last-move" bottom />
+        })
+    };
+
+    let board_label = move || match plies() {
+        0 => "Chess game, the start position".to_string(),
+        ply => format!("Chess game, after move {ply} of {len}"),
+    };
+
+    let speeds = SPEEDS
+        .into_iter()
+        .map(|(label, millis)| {
+            view! {
+                <option value=millis.to_string() selected=millis == DEFAULT_SPEED>
+                    {label}
+                </option>
+            }
+        })
+        .collect_view();
+
+    Either::Right(view! {
+        <div class="flex flex-col gap-4 items-center w-full md:flex-row md:items-start">
+            <div class="flex flex-col gap-2 items-center w-full max-w-180">
+                <div
+                    class="grid relative grid-cols-8 w-full bg-contain rounded-md pointer-events-none select-none bg-[url(/board.png)] aspect-square grid-rows-8"
+                    role="img"
+                    aria-label=board_label
+                >
+                    {highlights}
+                    <Pieces pieces=tracked bottom />
+                </div>
+                <div class="flex flex-row flex-wrap gap-2 justify-center items-center">
+                    <button on:click=on_play class="p-2 w-24 text-lg button-primary" disabled=len == 0>
+                        {move || match playing.get() {
+                            true => "Pause",
+                            false => "Play",
+                        }}
+                    </button>
+                    <label class="flex gap-1 items-center font-sans">
+                        "A move every"
+                        <select on:change=move |ev| {
+                            if let Ok(millis) = event_target_value(&ev).parse() {
+                                set_speed.set(millis);
+                            }
+                        }>{speeds}</select>
+                    </label>
+                    <button
+                        on:click=move |_| set_flipped.update(|flipped| *flipped = !*flipped)
+                        class="p-2 text-lg button-secondary"
+                        aria-pressed=move || flipped.get().to_string()
+                    >
+                        "Flip board"
+                    </button>
+                </div>
+            </div>
+            <MoveList notation viewing />
+        </div>
+    })
+}
+
+/// Every position of the game from the start, with the move and FEN that led to it.
+fn replay(
+    start: &StartPosition,
+    game: &Game,
+) -> Result<Vec<(Option<San>, Fen, VariantPosition)>, Error> {
+    let (start, moves) = match game {
+        Game::Pgn(pgn) => parse_pgn(start, pgn)?,
+        Game::Moves(moves) => (start.clone(), moves.clone()),
+    };
+
+    let mut pos = start.position()?;
+    let mut positions = vec![(
+        None,
+        Fen::from_position(&pos, EnPassantMode::Legal),
+        pos.clone(),
+    )];
+    // The positions come from the moves, a list that disagrees with its own FENs is still shown.
+    for (san, _fen) in moves {
+        let m = san.to_move(&pos).map_err(|_| Error::ImpossibleChessGame)?;
+        pos = pos.play(m).map_err(|_| Error::ImpossibleChessGame)?;
+        let fen = Fen::from_position(&pos, EnPassantMode::Legal);
+        positions.push((Some(san), fen, pos.clone()));
+    }
+    Ok(positions)
+}
+
+/// A game shared as a link, `/game?pgn=...` with the PGN in the query.
+/// Its `[Variant]` and `[FEN]` tags say where it starts.
+#[component]
+pub fn SharedGamePage() -> impl IntoView {
+    let query = use_query_map();
+
+    let viewer = move || match query.read().get("pgn") {
+        Some(pgn) => Either::Left(view! { <GameViewer game=Game::Pgn(pgn) /> }),
+        None => Either::Right(view! { <div>"There is no game in this link"</div> }),
+    };
+
+    view! {
+        <div class="flex overflow-y-auto flex-col gap-4 justify-start items-center p-5 w-full h-full">
+            <span class="w-full text-3xl h-fit">"Game"</span>
+            {viewer}
+        </div>
+    }
+}
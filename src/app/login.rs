@@ -1,8 +1,9 @@
 use crate::app::chess::ChessBoard;
 use crate::app::game_modal::*;
+use crate::app::pgn::PgnInput;
+use crate::types::start::StartPosition;
 use crate::types::Error;
-use gloo::storage::Storage;
-use leptos::either::EitherOf3;
+use leptos::either::{Either, EitherOf4};
 use leptos::logging::*;
 use leptos::prelude::*;
 use leptos_router::hooks::use_navigate;
@@ -10,6 +11,7 @@ use leptos_router::NavigateOptions;
 use shakmaty::fen::*;
 use shakmaty::san::*;
 use shakmaty::KnownOutcome;
+use std::time::Duration;
 
 #[derive(Clone, Debug, PartialEq)]
 enum State {
@@ -17,9 +19,14 @@ enum State {
     Password {
         user_name: String,
     },
+    SecondFactor {
+        user_name: String,
+        password: Vec<(San, Fen)>,
+    },
     Done {
         user_name: String,
-        password: Vec<Fen>,
+        password: Vec<(San, Fen)>,
+        totp: Option<String>,
     },
 }
 
@@ -37,7 +44,7 @@ pub fn LoginPage() -> impl IntoView {
                 })
             };
 
-            EitherOf3::A(view! {
+            EitherOf4::A(view! {
                 <div class="flex flex-col gap-2.5 justify-center items-center">
                     <span class="text-5xl">"Login"</span>
                     <input
@@ -49,6 +56,12 @@ pub fn LoginPage() -> impl IntoView {
                     <button on:click=on_click class="p-4 w-full text-2xl button-primary">
                         "Continue"
                     </button>
+                    <button
+                        on:click=move |_| { use_navigate()("/recover", NavigateOptions::default()) }
+                        class="p-2 w-full text-lg button-secondary"
+                    >
+                        "Forgot your game?"
+                    </button>
                 </div>
             })
         }
@@ -57,9 +70,9 @@ pub fn LoginPage() -> impl IntoView {
 
             let (ended, set_ended) = signal(Option::<KnownOutcome>::None);
 
-            let on_finished = move |o: KnownOutcome| {
+            let on_finished = move |o: Option<KnownOutcome>| {
                 log!("ended {o:?}");
-                set_ended.set(Some(o));
+                set_ended.set(o);
             };
 
             let on_continue = {
@@ -67,12 +80,55 @@ pub fn LoginPage() -> impl IntoView {
                 move |_| {
                     set_state.set(State::Done {
                         user_name: user_name.clone(),
-                        password: notation.get().into_iter().map(|(_san, fen)| fen).collect(),
+                        password: notation.get(),
+                        totp: None,
+                    });
+                }
+            };
+
+            let start = {
+                let user_name = user_name.clone();
+                LocalResource::new(move || get_start_position(user_name.clone()))
+            };
+
+            let on_pgn = {
+                let user_name = user_name.clone();
+                move |password| {
+                    set_state.set(State::Done {
+                        user_name: user_name.clone(),
+                        password,
+                        totp: None,
                     });
+                    Ok(())
                 }
             };
 
-            EitherOf3::B(view! {
+            let board = move || {
+                let on_continue = on_continue.clone();
+                let on_pgn = on_pgn.clone();
+                Suspend::new(async move {
+                    match start.await {
+                        Ok(start) => Either::Left(view! {
+                            <div class="flex flex-col gap-10 justify-around items-center w-full md:flex-row">
+                                <div class="flex flex-col justify-center items-center w-full max-w-180">
+                                    <ChessBoard on_finished notation start=start.clone() coordinates=true />
+                                    <GameEndModal ended on_continue />
+                                </div>
+                                <div class="w-full md:w-96">
+                                    <PgnInput start on_submit=on_pgn />
+                                </div>
+                            </div>
+                        }),
+                        Err(e) => Either::Right(view! {
+                            <div class="text-red-700">
+                                "Error loading the start position: " {e.to_string()}
+                            </div>
+                        }),
+                    }
+                })
+            };
+
+            EitherOf4::B(view! {
                 <div class="flex flex-col gap-2.5 justify-start items-center w-full h-full">
                     <div class="flex flex-col justify-start items-start w-full text-2xl h-fit">
                         "Hi " {user_name}
@@ -80,29 +136,84 @@ pub fn LoginPage() -> impl IntoView {
                             "Let's make a password! Play a game of chess with yourself until the game is over! Remember the game well!"
                         </span>
                     </div>
-                    <div class="flex flex-col justify-center items-center w-full h-full">
-                        <ChessBoard on_finished notation />
-                        <GameEndModal ended on_continue />
+                    <div class="flex flex-col justify-center items-center w-full md:h-full">
+                        <Suspense fallback=move || view! { <div>"Loading..."</div> }>{board}</Suspense>
                     </div>
                 </div>
             })
         }
+        State::SecondFactor {
+            user_name,
+            password,
+        } => {
+            let (code, set_code) = signal(String::new());
+
+            let on_click = move |_| {
+                set_state.set(State::Done {
+                    user_name: user_name.clone(),
+                    password: password.clone(),
+                    totp: Some(code.get()),
+                })
+            };
+
+            EitherOf4::C(view! {
+                <div class="flex flex-col gap-2.5 justify-center items-center">
+                    <span class="text-5xl">"Two-factor"</span>
+                    <span class="font-sans text-lg font-light">
+                        "Right game! Now enter the code from your authenticator app."
+                    </span>
+                    <input
+                        placeholder="123456"
+                        class="w-full font-mono"
+                        type="text"
+                        inputmode="numeric"
+                        autocomplete="one-time-code"
+                        bind:value=(code, set_code)
+                    />
+                    <button on:click=on_click class="p-4 w-full text-2xl button-primary">
+                        "Login"
+                    </button>
+                </div>
+            })
+        }
         State::Done {
             user_name,
             password,
+            totp,
         } => {
             let result = {
                 let user_name = user_name.clone();
-                LocalResource::new(move || login(user_name.clone(), password.clone()))
+                let password = password.clone();
+                LocalResource::new(move || login(user_name.clone(), password.clone(), totp.clone()))
             };
 
             let suspense = {
                 let user_name = user_name.clone();
                 move || {
                     let user_name = user_name.clone();
+                    let password = password.clone();
                     Suspend::new(async move {
                         let result = result.await;
 
+                        if let Err(Error::SecondFactorRequired) = &result {
+                            set_state.set(State::SecondFactor {
+                                user_name: user_name.clone(),
+                                password: password.clone(),
+                            });
+                        }
+
+                        let (retry_in, set_retry_in) = signal(0u64);
+                        if let Err(Error::TooManyAttempts { retry_after }) = &result {
+                            set_retry_in.set(*retry_after);
+                            match set_interval_with_handle(
+                                move || set_retry_in.update(|s| *s = s.saturating_sub(1)),
+                                Duration::from_secs(1),
+                            ) {
+                                Ok(handle) => on_cleanup(move || handle.clear()),
+                                Err(e) => error!("Could not start the lockout countdown: {e:?}"),
+                            }
+                        }
+
                         let (main_text, sub_text, button_text) = match &result {
                             Ok(_) => (
                                 "Logged in".to_string(),
@@ -114,6 +225,16 @@ pub fn LoginPage() -> impl IntoView {
                                 "You messed up".to_string(),
                                 "Try again".to_string(),
                             ),
+                            Err(Error::WrongSecondFactor) => (
+                                "Wrong code".to_string(),
+                                "The game was right, the code was not".to_string(),
+                                "Try again".to_string(),
+                            ),
+                            Err(Error::TooManyAttempts { .. }) => (
+                                "Too many attempts".to_string(),
+                                "Take a break and remember your game".to_string(),
+                                "Try again".to_string(),
+                            ),
                             Err(e) => (
                                 "Something went wrong".to_string(),
                                 e.to_string(),
@@ -121,67 +242,279 @@ pub fn LoginPage() -> impl IntoView {
                             ),
                         };
 
-                        if let Ok(user_id) = &result {
-                            if let Err(e) = gloo::storage::LocalStorage::set("id", user_id) {
-                                error!("Could not store user id in local storage: {e}");
-                            }
-                        }
+                        let button_text = Signal::derive(move || match retry_in.get() {
+                            0 => button_text.clone(),
+                            s => format!("Try again in {s}s"),
+                        });
+
+                        let visible = !matches!(result, Err(Error::SecondFactorRequired));
 
                         let on_click = move |_| match result {
                             Err(Error::WrongPassword) => set_state.set(State::Password {
                                 user_name: user_name.clone(),
                             }),
+                            Err(Error::WrongSecondFactor) => set_state.set(State::SecondFactor {
+                                user_name: user_name.clone(),
+                                password: password.clone(),
+                            }),
+                            Err(Error::TooManyAttempts { .. }) => {
+                                if retry_in.get_untracked() == 0 {
+                                    set_state.set(State::Password {
+                                        user_name: user_name.clone(),
+                                    })
+                                }
+                            }
                             _ => use_navigate()("/", NavigateOptions::default()),
                         };
 
-                        view! { <GameModal visible=true main_text sub_text button_text on_click /> }
+                        view! { <GameModal visible main_text sub_text button_text on_click /> }
                     })
                 }
             };
 
-            EitherOf3::C(view! { <Suspense>{suspense}</Suspense> })
+            EitherOf4::D(view! { <Suspense>{suspense}</Suspense> })
         }
     };
 
     view! {
-        <div class="flex flex-col justify-center items-center p-5 w-full h-full">
+        <div class="flex overflow-y-auto flex-col justify-start items-center p-5 w-full h-full md:justify-center">
             {current_view}
         </div>
     }
 }
 
 #[server]
-async fn login(name: String, password: Vec<Fen>) -> Result<String, Error> {
-    use crate::app::register::hash_fen_with_salt;
-    use crate::types::AppState;
+async fn login(name: String, password: Vec<(San, Fen)>, totp: Option<String>) -> Result<(), Error> {
+    use crate::types::username::lookup_username;
+    use crate::types::{record_auth_event, AppState, AuthEventKind, Session};
 
     let app_state = expect_context::<AppState>();
 
+    let name = lookup_username(&name);
+    let second_factor = SecondFactor::Code(totp.as_deref());
+    let user_id = authenticate(&app_state, &name, &password, second_factor).await?;
+
+    Session::start(&app_state, &user_id).await?;
+    record_auth_event(
+        &app_state.db,
+        Some(&user_id),
+        &name,
+        AuthEventKind::LoginSuccess,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// The position the password game of the user starts from, so the board can be set up before logging in.
+/// Names that don't exist get the normal start, the login itself tells them apart.
+#[server]
+pub async fn get_start_position(name: String) -> Result<StartPosition, Error> {
+    use crate::types::username::lookup_username;
+    use crate::types::AppState;
+    let app_state = expect_context::<AppState>();
+
+    let name = lookup_username(&name);
+
+    let user = sqlx::query!(
+        "SELECT variant, start_fen FROM users WHERE LOWER(username) = LOWER($1)",
+        name
+    )
+    .fetch_optional(&app_state.db.pool)
+    .await?;
+
+    match user {
+        Some(user) => StartPosition::parse(&user.variant, user.start_fen.as_deref()),
+        None => Ok(StartPosition::default()),
+    }
+}
+
+/// Whether [`authenticate`] asks for the authenticator code of users that turned it on.
+#[cfg(feature = "ssr")]
+pub enum SecondFactor<'a> {
+    /// The user already has a session that passed it.
+    Skip,
+    Code(Option<&'a str>),
+    /// A code for the secret that is being set up, checked before two-factor login is on.
+    Enrolling(&'a str),
+}
+
+/// Checks the game against the one stored for the user and returns the user id.
+/// The login throttle counts every attempt before the game is checked and takes it back on
+/// success, where legacy hashes are also upgraded.
+/// The authenticator code is only checked after a correct game, but before the throttle is reset.
+#[cfg(feature = "ssr")]
+pub async fn authenticate(
+    app_state: &crate::types::AppState,
+    name: &str,
+    password: &[(San, Fen)],
+    second_factor: SecondFactor<'_>,
+) -> Result<String, Error> {
+    use crate::app::register::check_chess_moves;
+    use crate::types::client::client_ip;
+    use crate::types::password::*;
+    use crate::types::throttle::LoginThrottle;
+    use crate::types::totp::verify_code;
+    use crate::types::AuthEventKind;
+
+    let throttle = LoginThrottle::new(name, client_ip());
+    // The lockout itself was logged by the attempt that caused it.
+    let attempt = throttle.check(&app_state.db).await?;
+
     let mut transaction = app_state.db.pool.begin().await?;
 
-    let data = sqlx::query!(
-        "SELECT salt, password, id FROM users WHERE username=$1",
+    let user = sqlx::query!(
+        r#"
+        SELECT id, salt, password, password_hash, hash_version, variant, start_fen,
+            totp_secret, totp_enabled, totp_last_step
+        FROM users WHERE LOWER(username) = LOWER($1)
+        "#,
         name
     )
     .fetch_optional(&mut *transaction)
-    .await?
-    .map(|r| (r.salt, r.password, r.id));
+    .await?;
 
-    let (salt, db_hashed_password, user_id) = match data {
-        Some((salt, pass, id)) => (salt, pass, id),
+    let user = match user {
+        Some(user) => user,
         None => {
+            record_failed_login(app_state, &attempt, None, name, AuthEventKind::UnknownUser)
+                .await?;
             return Err(Error::DoesNotExist(
                 "The user that you tried to login to doesn't exist".to_string(),
             ));
         }
     };
 
-    let fen_hashed_vec = hash_fen_with_salt(password, &salt);
+    let start = StartPosition::parse(&user.variant, user.start_fen.as_deref())?;
+    let moves = match check_chess_moves(start.position()?, password) {
+        Some(moves) => moves,
+        None => {
+            let kind = AuthEventKind::WrongPassword;
+            record_failed_login(app_state, &attempt, Some(&user.id), name, kind).await?;
+            return Err(Error::WrongPassword);
+        }
+    };
+    let game = canonical_game(&start, &moves);
 
-    if fen_hashed_vec != db_hashed_password {
+    let correct = match (user.hash_version, user.password_hash) {
+        (HASH_VERSION, Some(hash)) => {
+            verify_game(game.clone(), hash, app_state.pepper.clone()).await?
+        }
+        (HASH_VERSION, None) => {
+            return Err(Error::Hash(
+                "The user has no password hash stored".to_string(),
+            ))
+        }
+        (LEGACY_HASH_VERSION, _) => {
+            let fen = password.iter().map(|(_san, fen)| fen.clone()).collect();
+            let salt = user.salt.unwrap_or_default();
+            Some(hash_fen_with_salt(fen, &salt)) == user.password
+        }
+        (version, _) => {
+            return Err(Error::Hash(format!(
+                "Unknown password hash version {version}"
+            )))
+        }
+    };
+
+    if !correct {
+        let kind = AuthEventKind::WrongPassword;
+        record_failed_login(app_state, &attempt, Some(&user.id), name, kind).await?;
         return Err(Error::WrongPassword);
     }
 
+    let code = match (second_factor, user.totp_enabled) {
+        (SecondFactor::Code(Some(code)), true) | (SecondFactor::Enrolling(code), false) => {
+            Some(code)
+        }
+        (SecondFactor::Code(None), true) => {
+            // The game was right, asking for the code isn't a failed attempt.
+            throttle.release(&app_state.db).await?;
+            return Err(Error::SecondFactorRequired);
+        }
+        _ => None,
+    };
+
+    if let (Some(code), Some(secret)) = (code, &user.totp_secret) {
+        let Some(step) = verify_code(secret, code, user.totp_last_step) else {
+            let kind = AuthEventKind::WrongSecondFactor;
+            record_failed_login(app_state, &attempt, Some(&user.id), name, kind).await?;
+            return Err(Error::WrongSecondFactor);
+        };
+
+        sqlx::query!(
+            "UPDATE users SET totp_last_step = $1 WHERE id = $2",
+            step,
+            user.id
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+
+    throttle.reset(&app_state.db).await?;
+
+    if user.hash_version != HASH_VERSION {
+        let password_hash = hash_game(game, app_state.pepper.clone()).await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE users SET password_hash = $1, hash_version = $2, password = NULL, salt = NULL
+            WHERE id = $3
+            "#,
+            password_hash,
+            HASH_VERSION,
+            user.id
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+
     transaction.commit().await?;
-    Ok(user_id)
+
+    Ok(user.id)
+}
+
+/// Logs the failed attempt, and logs the lockout if counting this attempt caused one.
+#[cfg(feature = "ssr")]
+pub async fn record_failed_login(
+    app_state: &crate::types::AppState,
+    attempt: &crate::types::throttle::Attempt,
+    user_id: Option<&str>,
+    name: &str,
+    kind: crate::types::AuthEventKind,
+) -> Result<(), Error> {
+    use crate::types::{record_auth_event, AuthEventKind};
+
+    record_auth_event(&app_state.db, user_id, name, kind).await?;
+
+    let result = attempt.failed();
+    if let Err(Error::TooManyAttempts { .. }) = result {
+        record_auth_event(&app_state.db, user_id, name, AuthEventKind::Lockout).await?;
+    }
+    result
+}
+
+/// Same as [`authenticate`], but for the user of the current session, to confirm sensitive actions.
+#[cfg(feature = "ssr")]
+pub async fn reauthenticate(
+    app_state: &crate::types::AppState,
+    password: &[(San, Fen)],
+    second_factor: SecondFactor<'_>,
+) -> Result<crate::types::Session, Error> {
+    use crate::types::Session;
+
+    let session = Session::require(app_state).await?;
+
+    let user = sqlx::query!("SELECT username FROM users WHERE id = $1", session.user_id)
+        .fetch_one(&app_state.db.pool)
+        .await?;
+
+    let user_id = authenticate(app_state, &user.username, password, second_factor).await?;
+    if user_id != session.user_id {
+        return Err(Error::Forbidden(
+            "The game belongs to a different user".to_string(),
+        ));
+    }
+
+    Ok(session)
 }
@@ -6,21 +6,37 @@ use leptos_router::{
     path, NavigateOptions,
 };
 
+mod account;
 mod chess;
 mod feed;
 mod game_modal;
+mod game_viewer;
 mod login;
+mod move_list;
+mod password;
+mod pgn;
+mod recovery;
 mod register;
 mod register_or_login;
+mod security;
+mod sessions;
+mod settings;
 mod sidebar;
+mod totp;
 mod vote;
 
-use chess::ChessBoard;
+use account::{DeleteAccountPage, ExportDataPage};
 use feed::FeedPage;
+use game_viewer::SharedGamePage;
 use login::LoginPage;
+use recovery::{RecoverPage, RecoveryCodesPage};
 use register::RegisterPage;
 use register_or_login::RegisterOrLoginPage;
+use security::SecurityHistoryPage;
+use sessions::SessionsPage;
+use settings::{ChangePasswordPage, SettingsPage};
 use sidebar::Sidebar;
+use totp::TwoFactorPage;
 use vote::VotePage;
 
 pub fn shell(options: LeptosOptions) -> impl IntoView {
@@ -56,10 +72,20 @@ pub fn App() -> impl IntoView {
                 <Routes fallback=|| "Page not found.".into_view()>
                     <Route path=path!("/register") view=RegisterPage />
                     <Route path=path!("/login") view=LoginPage />
+                    <Route path=path!("/recover") view=RecoverPage />
                     <ParentRoute path=path!("") view=Sidebar>
                         <Route path=path!("feed") view=FeedPage />
                         <Route path=path!("play") view=VotePage />
+                        <Route path=path!("game") view=SharedGamePage />
                         <Route path=path!("login-register") view=RegisterOrLoginPage />
+                        <Route path=path!("settings") view=SettingsPage />
+                        <Route path=path!("settings/password") view=ChangePasswordPage />
+                        <Route path=path!("settings/recovery-codes") view=RecoveryCodesPage />
+                        <Route path=path!("settings/sessions") view=SessionsPage />
+                        <Route path=path!("settings/security") view=SecurityHistoryPage />
+                        <Route path=path!("settings/two-factor") view=TwoFactorPage />
+                        <Route path=path!("settings/export") view=ExportDataPage />
+                        <Route path=path!("settings/delete-account") view=DeleteAccountPage />
                         <Route path=path!("") view=RedirectToFeed />
                     </ParentRoute>
                 </Routes>
@@ -74,5 +100,4 @@ fn RedirectToFeed() -> impl IntoView {
         let navigate = use_navigate();
         navigate("/feed", NavigateOptions::default());
     });
-    view! {}
 }
@@ -0,0 +1,147 @@
+use leptos::either::Either;
+use leptos::ev::KeyboardEvent;
+use leptos::prelude::*;
+use shakmaty::fen::*;
+use shakmaty::san::*;
+use shakmaty::Color;
+
+/// The moves of a game as numbered pairs of SAN. Clicking a move, or the arrow keys while the list
+/// has focus, sets `viewing` to how many moves into the game the shown position is.
+/// `None` means following the game, the newest position.
+#[component]
+pub fn MoveList(
+    #[prop(into)] notation: Signal<Vec<(San, Fen)>>,
+    viewing: RwSignal<Option<usize>>,
+) -> impl IntoView {
+    let shown = move || viewing.get().unwrap_or_else(|| notation.read().len());
+
+    let go_to = move |plies: usize| {
+        let len = notation.read_untracked().len();
+        viewing.set((plies < len).then_some(plies));
+    };
+    let step = move |by: isize| {
+        let len = notation.read_untracked().len();
+        let plies = viewing.get_untracked().unwrap_or(len);
+        go_to(plies.saturating_add_signed(by).min(len));
+    };
+
+    let on_key_down = move |ev: KeyboardEvent| {
+        match ev.key().as_str() {
+            "ArrowLeft" | "ArrowUp" => step(-1),
+            "ArrowRight" | "ArrowDown" => step(1),
+            "Home" => go_to(0),
+            "End" => go_to(usize::MAX),
+            _ => return,
+        }
+        ev.prevent_default();
+    };
+
+    let move_button = move |ply: usize, san: San| {
+        view! {
+            <button
+                on:click=move |_| go_to(ply + 1)
+                class="py-0.5 px-1 font-sans shadow-none hover:bg-secondary-hover"
+                class:bg-secondary-hover=move || shown() == ply + 1
+                aria-current=move || (shown() == ply + 1).then_some("step")
+            >
+                {san.to_string()}
+            </button>
+        }
+    };
+
+    let rows = move || {
+        let notation = notation.get();
+        let Some((_san, first)) = notation.first() else {
+            return Either::Left(view! {
+                <span class="font-sans text-sm font-light">"No moves yet"</span>
+            });
+        };
+        let (number, mover) = first_move(first);
+
+        // Black moving first leaves the white half of the first row empty.
+        let offset = mover.fold_wb(0, 1);
+        let rows = (0..(notation.len() + offset).div_ceil(2))
+            .map(|row| {
+                let white = (row * 2).checked_sub(offset);
+                let black = row * 2 + 1 - offset;
+                let cell = |ply: Option<usize>| {
+                    ply.and_then(|ply| Some((ply, notation.get(ply)?.0)))
+                        .map(|(ply, san)| move_button(ply, san))
+                };
+
+                view! {
+                    <li class="contents">
+                        <span class="pr-2 font-sans text-sm font-light text-right">
+                            {format!("{}.", number as usize + row)}
+                        </span>
+                        <span>{cell(white)}</span>
+                        <span>{cell(Some(black))}</span>
+                    </li>
+                }
+            })
+            .collect_view();
+
+        Either::Right(view! { <ol class="grid grid-cols-[auto_1fr_1fr] items-center">{rows}</ol> })
+    };
+
+    let len = move || notation.read().len();
+
+    view! {
+        <div class="flex flex-col gap-2 w-full md:w-60">
+            <div
+                class="overflow-y-auto p-2 max-h-80 rounded-md outline-none bg-secondary focus:ring-2 focus:ring-sky-400"
+                tabindex="0"
+                aria-label="Moves. Step through them with the arrow keys."
+                on:keydown=on_key_down
+            >
+                {rows}
+            </div>
+            <div class="flex flex-row gap-2 justify-center">
+                <button
+                    on:click=move |_| go_to(0)
+                    class="px-3 button-secondary"
+                    aria-label="First position"
+                    disabled=move || shown() == 0
+                >
+                    "|<"
+                </button>
+                <button
+                    on:click=move |_| step(-1)
+                    class="px-3 button-secondary"
+                    aria-label="Previous move"
+                    disabled=move || shown() == 0
+                >
+                    "<"
+                </button>
+                <button
+                    on:click=move |_| step(1)
+                    class="px-3 button-secondary"
+                    aria-label="Next move"
+                    disabled=move || shown() == len()
+                >
+                    ">"
+                </button>
+                <button
+                    on:click=move |_| go_to(usize::MAX)
+                    class="px-3 button-secondary"
+                    aria-label="Current position"
+                    disabled=move || viewing.read().is_none()
+                >
+                    ">|"
+                </button>
+            </div>
+        </div>
+    }
+}
+
+/// The move number and the side of the first move, from the position after it.
+fn first_move(after: &Fen) -> (u32, Color) {
+    let setup = after.as_setup();
+    let mover = !setup.turn;
+    // The number only goes up once black has moved.
+    let number = match mover {
+        Color::White => setup.fullmoves.get(),
+        Color::Black => setup.fullmoves.get().saturating_sub(1).max(1),
+    };
+    (number, mover)
+}
@@ -0,0 +1,461 @@
+use crate::app::chess::ChessBoard;
+use crate::app::game_modal::*;
+use crate::app::move_list::MoveList;
+use crate::app::pgn::PgnInput;
+use crate::types::policy::PasswordPolicy;
+use crate::types::start::*;
+use crate::types::strength::*;
+use crate::types::Error;
+use leptos::either::EitherOf3;
+use leptos::logging::*;
+use leptos::prelude::*;
+use shakmaty::fen::*;
+use shakmaty::san::*;
+use shakmaty::variant::{Variant, VariantPosition};
+use shakmaty::KnownOutcome;
+
+#[component]
+pub fn PlayPassword(
+    #[prop(into)] user_name: String,
+    #[prop(into)] intro: String,
+    on_continue: impl Fn(Vec<(San, Fen)>) + Clone + Send + Sync + 'static,
+    /// Show the strength meter and only allow continuing with a strong enough game.
+    #[prop(optional)]
+    check_strength: bool,
+    #[prop(optional)] start: StartPosition,
+    /// Also take the game as a PGN.
+    #[prop(optional)]
+    allow_pgn: bool,
+) -> impl IntoView {
+    let notation: RwSignal<Vec<(San, Fen)>> = RwSignal::new(vec![]);
+
+    let start_pos = start
+        .position()
+        .unwrap_or_else(|_| VariantPosition::new(start.variant));
+
+    // New games follow the rules of the server, replaying an existing game only needs a move.
+    let server_policy = LocalResource::new(move || async move {
+        match check_strength {
+            true => get_password_policy().await.map(Some),
+            false => Ok(None),
+        }
+    });
+    // Nothing is checked against the rules until they arrived, the defaults might be wrong.
+    let policy = Signal::derive(move || server_policy.get().map(loaded_policy));
+
+    let on_pgn = {
+        let on_continue = on_continue.clone();
+        let start_pos = start_pos.clone();
+        move |game: Vec<(San, Fen)>| {
+            // The server checks the game again either way.
+            if let Some(policy) = &*policy.read_untracked() {
+                policy.check(&start_pos, &game)?;
+            }
+            let sans = game.iter().map(|(san, _fen)| *san).collect::<Vec<_>>();
+            if check_strength && !PasswordStrength::estimate(&start_pos, &sans).is_acceptable() {
+                return Err(Error::WeakPassword);
+            }
+            on_continue(game);
+            Ok(())
+        }
+    };
+    let pgn_input = allow_pgn.then(|| view! { <PgnInput start=start.clone() on_submit=on_pgn /> });
+
+    let problem = {
+        let start_pos = start_pos.clone();
+        Memo::new(move |_| match &*policy.read() {
+            Some(policy) => policy
+                .check(&start_pos, &notation.read())
+                .err()
+                .map(|e| e.to_string()),
+            None => Some("Loading the rules...".to_string()),
+        })
+    };
+
+    let strength = Memo::new(move |_| {
+        let sans = notation
+            .read()
+            .iter()
+            .map(|(san, _fen)| *san)
+            .collect::<Vec<_>>();
+        PasswordStrength::estimate(&start_pos, &sans)
+    });
+
+    let strong_enough = move || !check_strength || strength.read().is_acceptable();
+    let can_continue = move || problem.read().is_none() && strong_enough();
+
+    let viewing = RwSignal::new(None);
+
+    let (ended, set_ended) = signal(Option::<KnownOutcome>::None);
+
+    let on_finished = move |o: Option<KnownOutcome>| {
+        log!("ended {o:?}");
+        set_ended.set(o);
+    };
+
+    let on_continue = move |_| {
+        if !can_continue() {
+            return;
+        }
+
+        on_continue(notation.get());
+    };
+
+    view! {
+        <div class="flex flex-col gap-2.5 justify-start items-center w-full h-full">
+            <div class="flex flex-col justify-start items-start w-full text-2xl h-fit">
+                "Hi " {user_name} <span class="font-sans font-light">{intro}</span>
+            </div>
+            <div class="flex flex-col gap-4 justify-around items-center w-full md:flex-row md:h-full">
+                <div class="flex flex-col justify-center items-center w-full max-w-180">
+                    <ChessBoard on_finished notation start coordinates=true viewing />
+                    <GameEndModal ended on_continue=on_continue.clone() />
+                </div>
+
+                <div class="flex flex-col gap-4 items-center">
+                    <MoveList notation viewing />
+                    {check_strength
+                        .then(|| {
+                            view! {
+                                <StrengthMeter strength />
+                                <Suspense fallback=move || {
+                                    view! {
+                                        <span class="font-sans text-lg font-light">
+                                            "Loading the rules..."
+                                        </span>
+                                    }
+                                }>
+                                    {move || Suspend::new(async move {
+                                        let policy = loaded_policy(server_policy.await);
+                                        view! { <PasswordRequirements policy /> }
+                                    })}
+                                </Suspense>
+                            }
+                        })}
+                    <button
+                        on:click=on_continue
+                        class="p-10 text-2xl"
+                        class:button-primary=can_continue
+                        class:button-secondary=move || !can_continue()
+                        class:bg-secondary-hover=move || !can_continue()
+                    >
+                        {move || {
+                            if let Some(problem) = problem.get() {
+                                EitherOf3::A(problem)
+                            } else if !strong_enough() {
+                                EitherOf3::B("Too easy to guess")
+                            } else {
+                                EitherOf3::C("Continue")
+                            }
+                        }}
+                    </button>
+                    {pgn_input}
+                </div>
+            </div>
+        </div>
+    }
+}
+
+/// The rules from the server, or only one move for replaying an existing game.
+fn loaded_policy(result: Result<Option<PasswordPolicy>, Error>) -> PasswordPolicy {
+    match result {
+        Ok(None) => PasswordPolicy {
+            min_plies: 1,
+            ..PasswordPolicy::default()
+        },
+        Ok(Some(policy)) => policy,
+        Err(e) => {
+            error!("Error loading the password policy: {e}");
+            PasswordPolicy::default()
+        }
+    }
+}
+
+/// The rules of the password policy as a list, so nobody has to find them out by failing.
+#[component]
+pub fn PasswordRequirements(policy: PasswordPolicy) -> impl IntoView {
+    let requirements = policy
+        .requirements()
+        .into_iter()
+        .map(|requirement| view! { <li>{requirement}</li> })
+        .collect_view();
+
+    view! { <ul class="font-sans text-lg font-light list-disc">{requirements}</ul> }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum StartKind {
+    Standard,
+    Chess960,
+    Custom,
+}
+
+/// Lets the user pick the variant and where their password game starts, writes the result into `start`.
+#[component]
+pub fn ChooseStart(start: RwSignal<Result<StartPosition, Error>>) -> impl IntoView {
+    let (variant, set_variant) = signal(Variant::Chess);
+    let (kind, set_kind) = signal(StartKind::Standard);
+    // The start position is shown to anyone who types in the name, so it doesn't need a real rng.
+    let random = chrono::Utc::now().timestamp_subsec_nanos() % CHESS960_POSITIONS;
+    let (number, set_number) = signal(random.to_string());
+    let (custom, set_custom) = signal(String::new());
+
+    Effect::new(move || {
+        let variant = variant.get();
+        let result = match kind.get() {
+            StartKind::Standard => Ok(StartPosition { variant, fen: None }),
+            StartKind::Chess960 => number
+                .read()
+                .trim()
+                .parse::<u32>()
+                .ok()
+                .and_then(chess960_fen)
+                .ok_or_else(|| {
+                    Error::InvalidStartPosition(format!(
+                        "Chess960 positions go from 0 to {}",
+                        CHESS960_POSITIONS - 1
+                    ))
+                })
+                .and_then(|fen| {
+                    let start = StartPosition {
+                        variant,
+                        fen: Some(fen),
+                    };
+                    start.position()?;
+                    Ok(start)
+                }),
+            StartKind::Custom => StartPosition::parse(variant.uci(), Some(custom.read().as_str())),
+        };
+        start.set(result);
+    });
+
+    let variants = Variant::ALL
+        .into_iter()
+        .map(|v| view! { <option value=v.uci() selected=v == Variant::Chess>{variant_name(v)}</option> })
+        .collect_view();
+
+    let on_variant = move |ev| {
+        if let Ok(v) = Variant::from_uci(&event_target_value(&ev)) {
+            set_variant.set(v);
+        }
+    };
+
+    let kind_button = move |k: StartKind, text: &'static str| {
+        view! {
+            <button
+                on:click=move |_| set_kind.set(k)
+                class="p-2 w-full text-lg"
+                class:button-primary=move || kind.get() == k
+                class:button-secondary=move || kind.get() != k
+            >
+                {text}
+            </button>
+        }
+    };
+
+    let input = move || match kind.get() {
+        StartKind::Standard => EitherOf3::A(()),
+        StartKind::Chess960 => EitherOf3::B(view! {
+            <input
+                placeholder="Position number"
+                class="w-full"
+                type="number"
+                min="0"
+                max=CHESS960_POSITIONS - 1
+                bind:value=(number, set_number)
+            />
+        }),
+        StartKind::Custom => EitherOf3::C(view! {
+            <input placeholder="FEN" class="w-full" type="text" bind:value=(custom, set_custom) />
+        }),
+    };
+
+    let error = move || match start.get() {
+        Err(Error::InvalidStartPosition(e)) => e,
+        Err(e) => e.to_string(),
+        Ok(_) => String::new(),
+    };
+
+    view! {
+        <div class="flex flex-col gap-2.5 w-full">
+            <span class="font-sans text-lg font-light">"Play by the rules of"</span>
+            <select class="w-full" on:change=on_variant>
+                {variants}
+            </select>
+            <span class="font-sans text-lg font-light">"Start the game from"</span>
+            <div class="flex flex-row gap-2.5 w-full">
+                {kind_button(StartKind::Standard, "Normal")}
+                {kind_button(StartKind::Chess960, "Chess960")}
+                {kind_button(StartKind::Custom, "FEN")}
+            </div>
+            {input}
+            <span class="text-red-500">{error}</span>
+        </div>
+    }
+}
+
+#[component]
+fn StrengthMeter(#[prop(into)] strength: Signal<PasswordStrength>) -> impl IntoView {
+    let level = move || strength.read().level();
+    let width = move || {
+        let filled = (strength.read().bits / STRONG_STRENGTH_BITS).clamp(0.0, 1.0);
+        format!("{}%", filled * 100.0)
+    };
+
+    let hints = move || {
+        let strength = strength.get();
+        let mut hints = vec![];
+        if let Some((name, plies)) = strength.opening {
+            hints.push(format!("The first {plies} moves are the {name}"));
+        }
+        if strength.forced_plies > 0 {
+            hints.push(format!("{} moves were forced", strength.forced_plies));
+        }
+        if strength.repeated_plies > 0 {
+            hints.push(format!(
+                "{} moves went back to an earlier position",
+                strength.repeated_plies
+            ));
+        }
+        hints
+            .into_iter()
+            .map(|hint| view! { <span class="font-sans text-sm font-light">{hint}</span> })
+            .collect_view()
+    };
+
+    view! {
+        <div class="flex flex-col gap-1 w-60">
+            <span class="text-xl">"Strength: " {move || level().label()}</span>
+            <div class="w-full h-3 rounded-full bg-secondary">
+                <div
+                    class="h-full rounded-full transition-all duration-300"
+                    class:bg-red-700=move || level() == StrengthLevel::VeryWeak
+                    class:bg-orange-500=move || level() == StrengthLevel::Weak
+                    class:bg-yellow-400=move || level() == StrengthLevel::Fair
+                    class:bg-green-600=move || level() == StrengthLevel::Strong
+                    style:width=width
+                />
+            </div>
+            {hints}
+        </div>
+    }
+}
+
+#[component]
+pub fn ConfirmPassword(
+    #[prop(into)] user_name: String,
+    first_attempt: Vec<(San, Fen)>,
+    on_confirmed: impl Fn(Vec<(San, Fen)>) + Clone + Send + Sync + 'static,
+    #[prop(optional)] start: StartPosition,
+    /// Also take the game as a PGN.
+    #[prop(optional)]
+    allow_pgn: bool,
+) -> impl IntoView {
+    let notation: RwSignal<Vec<(San, Fen)>> = RwSignal::new(vec![]);
+
+    let on_pgn = {
+        let first_attempt = first_attempt.clone();
+        let on_confirmed = on_confirmed.clone();
+        move |game: Vec<(San, Fen)>| {
+            if game != first_attempt {
+                return Err(Error::WrongPassword);
+            }
+            on_confirmed(game);
+            Ok(())
+        }
+    };
+    let pgn_input = allow_pgn.then(|| {
+        view! {
+            <div class="w-full md:w-96">
+                <PgnInput start=start.clone() on_submit=on_pgn />
+            </div>
+        }
+    });
+
+    let viewing = RwSignal::new(None);
+
+    let matches = {
+        let first = first_attempt.clone();
+        move || {
+            let notation = notation.get();
+            let last_element = match notation.last() {
+                Some(e) => e,
+                None => return true,
+            };
+
+            let first_element = match first.get(notation.len() - 1) {
+                Some(e) => e,
+                None => return false,
+            };
+
+            last_element == first_element
+        }
+    };
+
+    let completed = {
+        let first_attempt = first_attempt.clone();
+        let matches = matches.clone();
+        move || notation.read().len() == first_attempt.len() && matches()
+    };
+
+    let (ended, set_ended) = signal(Option::<KnownOutcome>::None);
+
+    let on_finished = move |o: Option<KnownOutcome>| {
+        log!("ended {o:?}");
+        set_ended.set(o);
+    };
+
+    let on_continue = move |_| {
+        if first_attempt == notation.get() {
+            on_confirmed(notation.get());
+        } else {
+            error!("Passwords do not match");
+        }
+    };
+
+    // Taking back the move that went wrong, the board follows the notation.
+    let on_take_back = move |_| {
+        notation.write().pop();
+    };
+
+    view! {
+        <div class="flex flex-col gap-2.5 justify-start items-center w-full h-full">
+            <div class="flex flex-col justify-start items-start w-full text-2xl h-fit">
+                "Hi " {user_name}
+                <span class="font-sans font-light">"Now play the same game of chess again!"</span>
+            </div>
+            <div class="flex flex-col gap-4 justify-around items-center w-full md:flex-row md:h-full">
+                <div class="flex flex-col justify-center items-center w-full max-w-180 md:h-full">
+                    <ChessBoard on_finished notation start coordinates=true viewing />
+                    <GameEndModal ended on_continue=on_continue.clone() />
+                    <GameModal
+                        visible=Signal::derive(move || !matches())
+                        main_text="Move doesn't match"
+                        sub_text="Take it back and try another one"
+                        button_text="Take back"
+                        on_click=on_take_back
+                    />
+                    <GameModal
+                        visible=Signal::derive(completed)
+                        main_text="Password Matches!!"
+                        button_text="Continue!"
+                        on_click=on_continue
+                    />
+                </div>
+                <div class="flex flex-col gap-4 items-center">
+                    <MoveList notation viewing />
+                    {pgn_input}
+                </div>
+            </div>
+        </div>
+    }
+}
+
+/// The rules new password games have to follow on this server.
+#[server]
+pub async fn get_password_policy() -> Result<PasswordPolicy, Error> {
+    use crate::types::AppState;
+    let app_state = expect_context::<AppState>();
+
+    Ok(app_state.password_policy.clone())
+}
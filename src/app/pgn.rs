@@ -0,0 +1,66 @@
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use shakmaty::fen::*;
+use shakmaty::san::*;
+
+use crate::types::pgn::parse_pgn;
+use crate::types::start::StartPosition;
+use crate::types::Error;
+
+/// Pasting or uploading the password game as a PGN, instead of clicking through the moves.
+/// `on_submit` can still turn the game down, its error is shown under the input.
+#[component]
+pub fn PgnInput(
+    #[prop(optional)] start: StartPosition,
+    on_submit: impl Fn(Vec<(San, Fen)>) -> Result<(), Error> + Send + Sync + 'static,
+) -> impl IntoView {
+    let (pgn, set_pgn) = signal(String::new());
+    let (error, set_error) = signal(None::<String>);
+
+    let on_file = move |ev: leptos::ev::Event| {
+        let file = event_target::<leptos::web_sys::HtmlInputElement>(&ev)
+            .files()
+            .and_then(|files| files.get(0));
+        let Some(file) = file else {
+            return;
+        };
+
+        spawn_local(async move {
+            match gloo::file::futures::read_as_text(&gloo::file::File::from(file)).await {
+                Ok(text) => set_pgn.set(text),
+                Err(e) => set_error.set(Some(format!("Could not read the file: {e}"))),
+            }
+        });
+    };
+
+    let on_click = move |_| {
+        let result = parse_pgn(&start, &pgn.read()).and_then(|(pgn_start, game)| {
+            if !pgn_start.is_same_start(&start) {
+                return Err(Error::InvalidPgn(
+                    "It starts from another position than this board".to_string(),
+                ));
+            }
+            on_submit(game)
+        });
+        set_error.set(result.err().map(|e| e.to_string()));
+    };
+
+    view! {
+        <div class="flex flex-col gap-2.5 w-full">
+            <span class="font-sans text-lg font-light">
+                "Or paste the game as PGN, or upload the file"
+            </span>
+            <textarea
+                placeholder="1. e4 e5 2. Nf3 Nc6 ..."
+                class="w-full h-32 font-mono"
+                prop:value=pgn
+                on:input=move |ev| set_pgn.set(event_target_value(&ev))
+            />
+            <input type="file" accept=".pgn,text/plain" on:change=on_file />
+            <span class="text-red-500">{move || error.get()}</span>
+            <button on:click=on_click class="p-4 w-full text-2xl button-secondary">
+                "Use PGN"
+            </button>
+        </div>
+    }
+}
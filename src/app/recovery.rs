@@ -0,0 +1,685 @@
+use leptos::either::{Either, EitherOf3, EitherOf6};
+use leptos::prelude::*;
+use leptos_router::hooks::use_navigate;
+use leptos_router::NavigateOptions;
+use shakmaty::fen::*;
+use shakmaty::san::*;
+
+use crate::app::game_modal::*;
+use crate::app::login::get_start_position;
+use crate::app::password::*;
+use crate::app::sidebar::get_user_name;
+use crate::types::start::StartPosition;
+use crate::types::Error;
+
+#[cfg(feature = "ssr")]
+const RECOVERY_CODE_COUNT: usize = 8;
+#[cfg(feature = "ssr")]
+const RECOVERY_CODE_LENGTH: usize = 10;
+/// No 0/o, 1/l/i, so the codes survive being written down by hand.
+#[cfg(feature = "ssr")]
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+#[component]
+pub fn RecoveryCodes(codes: Vec<String>) -> impl IntoView {
+    let codes = codes
+        .into_iter()
+        .map(|code| view! { <span class="p-2 font-mono text-xl rounded-md bg-background">{code}</span> })
+        .collect_view();
+
+    view! {
+        <div class="flex flex-col gap-4 items-center p-4 rounded-2xl bg-secondary">
+            <span class="text-2xl">"Your recovery codes"</span>
+            <span class="font-sans text-lg font-light">
+                "If you ever forget your game, one of these lets you set a new one. Every code works once. Write them down, you won't see them again!"
+            </span>
+            <div class="grid grid-cols-2 gap-2">{codes}</div>
+        </div>
+    }
+}
+
+#[component]
+pub fn RecoveryCodesPage() -> impl IntoView {
+    let user = Resource::new(
+        || (),
+        |_| async move {
+            match get_user_name().await? {
+                Some(name) => {
+                    let start = get_start_position(name.clone()).await?;
+                    Ok(Some((name, start)))
+                }
+                None => Ok::<_, Error>(None),
+            }
+        },
+    );
+
+    Effect::new(move || {
+        if let Some(Ok(None)) = user.get() {
+            use_navigate()("/login-register", NavigateOptions::default());
+        }
+    });
+
+    let suspense = move || {
+        Suspend::new(async move {
+            match user.await {
+                Ok(Some((user_name, start))) => {
+                    EitherOf3::A(view! { <RegenerateRecoveryCodes user_name start /> })
+                }
+                Ok(None) => EitherOf3::B(view! { <div>"Not logged in"</div> }),
+                Err(e) => EitherOf3::C(
+                    view! { <div class="text-red-700">"Error loading username: " {e.to_string()}</div> },
+                ),
+            }
+        })
+    };
+
+    view! {
+        <div class="flex overflow-y-auto flex-col justify-start items-center p-5 w-full h-full md:justify-center">
+            <Transition fallback=move || view! { <div>"Loading..."</div> }>{suspense}</Transition>
+        </div>
+    }
+}
+
+#[component]
+fn RegenerateRecoveryCodes(user_name: String, start: StartPosition) -> impl IntoView {
+    let (password, set_password) = signal(None::<Vec<(San, Fen)>>);
+
+    let current_view = move || match password.get() {
+        None => {
+            let on_continue = move |password| set_password.set(Some(password));
+
+            Either::Left(view! {
+                <PlayPassword
+                    user_name=user_name.clone()
+                    start=start.clone()
+                    intro="Replay your game to get new recovery codes. All of your old ones stop working."
+                    allow_pgn=true
+                    on_continue
+                />
+            })
+        }
+        Some(password) => {
+            let result = LocalResource::new(move || regenerate_recovery_codes(password.clone()));
+
+            let on_settings = move |_| use_navigate()("/settings", NavigateOptions::default());
+
+            Either::Right(view! {
+                <Suspense>
+                    {move || Suspend::new(async move {
+                        match result.await {
+                            Ok(codes) => Either::Left(view! {
+                                <div class="flex flex-col gap-4 items-center">
+                                    <RecoveryCodes codes />
+                                    <button on:click=on_settings class="p-4 w-full text-2xl button-primary">
+                                        "Back to settings"
+                                    </button>
+                                </div>
+                            }),
+                            Err(e) => {
+                                let (main_text, sub_text) = match e {
+                                    Error::WrongPassword => (
+                                        "Wrong password".to_string(),
+                                        "That is not your game".to_string(),
+                                    ),
+                                    e => ("Something went wrong".to_string(), e.to_string()),
+                                };
+                                let on_click = move |_| set_password.set(None);
+
+                                Either::Right(view! {
+                                    <GameModal
+                                        visible=true
+                                        main_text
+                                        sub_text
+                                        button_text="Try again"
+                                        on_click
+                                    />
+                                })
+                            }
+                        }
+                    })}
+                </Suspense>
+            })
+        }
+    };
+
+    view! { {current_view} }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum State {
+    Code,
+    Checking {
+        user_name: String,
+        code: String,
+    },
+    Password {
+        user_name: String,
+        code: String,
+        start: StartPosition,
+    },
+    PasswordConfirm {
+        user_name: String,
+        code: String,
+        start: StartPosition,
+        first_attempt: Vec<(San, Fen)>,
+    },
+    SecondFactor {
+        user_name: String,
+        code: String,
+        password: Vec<(San, Fen)>,
+    },
+    Done {
+        user_name: String,
+        code: String,
+        password: Vec<(San, Fen)>,
+        totp: Option<String>,
+    },
+}
+
+#[component]
+pub fn RecoverPage() -> impl IntoView {
+    let (state, set_state) = signal(State::Code);
+
+    let current_view = move || match state.get() {
+        State::Code => {
+            let (user_name, set_username) = signal(String::new());
+            let (code, set_code) = signal(String::new());
+
+            let on_click = move |_| {
+                set_state.set(State::Checking {
+                    user_name: user_name.get(),
+                    code: code.get(),
+                })
+            };
+
+            EitherOf6::A(view! {
+                <div class="flex flex-col gap-2.5 justify-center items-center">
+                    <span class="text-5xl">"Recover"</span>
+                    <span class="font-sans text-lg font-light">
+                        "Forgot your game? Use one of your recovery codes to set a new one."
+                    </span>
+                    <input
+                        placeholder="Name"
+                        class="w-full"
+                        type="text"
+                        bind:value=(user_name, set_username)
+                    />
+                    <input
+                        placeholder="Recovery code"
+                        class="w-full"
+                        type="text"
+                        bind:value=(code, set_code)
+                    />
+                    <button on:click=on_click class="p-4 w-full text-2xl button-primary">
+                        "Continue"
+                    </button>
+                </div>
+            })
+        }
+        State::Checking { user_name, code } => {
+            let result = {
+                let user_name = user_name.clone();
+                let code = code.clone();
+                LocalResource::new(move || {
+                    let user_name = user_name.clone();
+                    let code = code.clone();
+                    async move {
+                        check_recovery_code(user_name.clone(), code).await?;
+                        get_start_position(user_name).await
+                    }
+                })
+            };
+
+            let suspense = move || {
+                let user_name = user_name.clone();
+                let code = code.clone();
+                Suspend::new(async move {
+                    let result = result.await;
+
+                    if let Ok(start) = &result {
+                        set_state.set(State::Password {
+                            user_name,
+                            code,
+                            start: start.clone(),
+                        });
+                    }
+
+                    let sub_text = match &result {
+                        Ok(_) => String::new(),
+                        Err(e) => e.to_string(),
+                    };
+
+                    let on_click = move |_| set_state.set(State::Code);
+
+                    view! {
+                        <GameModal
+                            visible=result.is_err()
+                            main_text="Could not recover"
+                            sub_text
+                            button_text="Try again"
+                            on_click
+                        />
+                    }
+                })
+            };
+
+            EitherOf6::B(view! { <Suspense>{suspense}</Suspense> })
+        }
+        State::Password {
+            user_name,
+            code,
+            start,
+        } => {
+            let on_continue = {
+                let user_name = user_name.clone();
+                let start = start.clone();
+                move |first_attempt| {
+                    set_state.set(State::PasswordConfirm {
+                        user_name: user_name.clone(),
+                        code: code.clone(),
+                        start: start.clone(),
+                        first_attempt,
+                    })
+                }
+            };
+
+            EitherOf6::C(view! {
+                <PlayPassword
+                    user_name
+                    start
+                    check_strength=true
+                    intro="The code is valid! Play the game that will be your new password, and remember it well this time!"
+                    on_continue
+                />
+            })
+        }
+        State::PasswordConfirm {
+            user_name,
+            code,
+            start,
+            first_attempt,
+        } => {
+            let on_confirmed = {
+                let user_name = user_name.clone();
+                move |password| {
+                    set_state.set(State::Done {
+                        user_name: user_name.clone(),
+                        code: code.clone(),
+                        password,
+                        totp: None,
+                    })
+                }
+            };
+
+            EitherOf6::D(view! { <ConfirmPassword user_name start first_attempt on_confirmed /> })
+        }
+        State::SecondFactor {
+            user_name,
+            code,
+            password,
+        } => {
+            let (totp, set_totp) = signal(String::new());
+
+            let on_click = move |_| {
+                set_state.set(State::Done {
+                    user_name: user_name.clone(),
+                    code: code.clone(),
+                    password: password.clone(),
+                    totp: Some(totp.get()),
+                })
+            };
+
+            EitherOf6::E(view! {
+                <div class="flex flex-col gap-2.5 justify-center items-center">
+                    <span class="text-5xl">"Two-factor"</span>
+                    <span class="font-sans text-lg font-light">
+                        "Two-factor login stays on, enter the code from your authenticator app to set the new game."
+                    </span>
+                    <input
+                        placeholder="123456"
+                        class="w-full font-mono"
+                        type="text"
+                        inputmode="numeric"
+                        autocomplete="one-time-code"
+                        bind:value=(totp, set_totp)
+                    />
+                    <button on:click=on_click class="p-4 w-full text-2xl button-primary">
+                        "Continue"
+                    </button>
+                </div>
+            })
+        }
+        State::Done {
+            user_name,
+            code,
+            password,
+            totp,
+        } => {
+            let result = {
+                let user_name = user_name.clone();
+                let code = code.clone();
+                let password = password.clone();
+                LocalResource::new(move || {
+                    recover_account(
+                        user_name.clone(),
+                        code.clone(),
+                        password.clone(),
+                        totp.clone(),
+                    )
+                })
+            };
+
+            let suspense = move || {
+                let user_name = user_name.clone();
+                let code = code.clone();
+                let password = password.clone();
+                Suspend::new(async move {
+                    let result = result.await;
+
+                    if let Err(Error::SecondFactorRequired) = &result {
+                        set_state.set(State::SecondFactor {
+                            user_name: user_name.clone(),
+                            code: code.clone(),
+                            password: password.clone(),
+                        });
+                    }
+
+                    let visible = !matches!(result, Err(Error::SecondFactorRequired));
+                    let wrong_second_factor = matches!(result, Err(Error::WrongSecondFactor));
+                    let (main_text, sub_text) = match result {
+                        Ok(_) => (
+                            "Password changed".to_string(),
+                            "That code is used up now, you have the others left".to_string(),
+                        ),
+                        Err(e) => ("Something went wrong".to_string(), e.to_string()),
+                    };
+
+                    let on_click = move |_| {
+                        if wrong_second_factor {
+                            set_state.set(State::SecondFactor {
+                                user_name: user_name.clone(),
+                                code: code.clone(),
+                                password: password.clone(),
+                            });
+                        } else {
+                            use_navigate()("/", NavigateOptions::default());
+                        }
+                    };
+                    let button_text = match wrong_second_factor {
+                        true => "Try again",
+                        false => "Home!",
+                    };
+
+                    view! { <GameModal visible main_text sub_text button_text on_click /> }
+                })
+            };
+
+            EitherOf6::F(view! { <Suspense>{suspense}</Suspense> })
+        }
+    };
+
+    view! {
+        <div class="flex overflow-y-auto flex-col justify-start items-center p-5 w-full h-full md:justify-center">
+            {current_view}
+        </div>
+    }
+}
+
+/// Replaces the codes of the user of the current session, after they replayed their game.
+#[server]
+async fn regenerate_recovery_codes(password: Vec<(San, Fen)>) -> Result<Vec<String>, Error> {
+    use crate::app::login::{reauthenticate, SecondFactor};
+    use crate::types::{record_auth_event, AppState, AuthEventKind};
+    let app_state = expect_context::<AppState>();
+
+    let session = reauthenticate(&app_state, &password, SecondFactor::Skip).await?;
+
+    let mut transaction = app_state.db.pool.begin().await?;
+    let codes = replace_recovery_codes(&mut transaction, &session.user_id).await?;
+    let user = sqlx::query!("SELECT username FROM users WHERE id = $1", session.user_id)
+        .fetch_one(&mut *transaction)
+        .await?;
+    transaction.commit().await?;
+
+    record_auth_event(
+        &app_state.db,
+        Some(&session.user_id),
+        &user.username,
+        AuthEventKind::RecoveryCodesRegenerated,
+    )
+    .await?;
+
+    Ok(codes)
+}
+
+/// Deletes all codes of the user and stores a fresh set, returns the codes in plain text to show them once.
+#[cfg(feature = "ssr")]
+pub async fn replace_recovery_codes(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: &str,
+) -> Result<Vec<String>, Error> {
+    use rand::Rng;
+
+    let codes = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code = (0..RECOVERY_CODE_LENGTH)
+                .map(|_| {
+                    RECOVERY_CODE_ALPHABET
+                        [rand::rng().random_range(0..RECOVERY_CODE_ALPHABET.len())]
+                        as char
+                })
+                .collect::<String>();
+            let (first, second) = code.split_at(RECOVERY_CODE_LENGTH / 2);
+            format!("{first}-{second}")
+        })
+        .collect::<Vec<_>>();
+
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut **transaction)
+        .await?;
+
+    for code in &codes {
+        sqlx::query!(
+            "INSERT INTO recovery_codes (id, user_id, code_hash) VALUES ($1, $2, $3)",
+            cuid2::cuid(),
+            user_id,
+            hash_recovery_code(code)
+        )
+        .execute(&mut **transaction)
+        .await?;
+    }
+
+    Ok(codes)
+}
+
+#[server]
+async fn check_recovery_code(name: String, code: String) -> Result<(), Error> {
+    use crate::types::client::client_ip;
+    use crate::types::throttle::LoginThrottle;
+    use crate::types::username::lookup_username;
+    use crate::types::AppState;
+    let app_state = expect_context::<AppState>();
+
+    let name = lookup_username(&name);
+    let throttle = LoginThrottle::new(&name, client_ip());
+    let attempt = throttle.check(&app_state.db).await?;
+
+    find_recovery_user(&app_state, &attempt, &name, &code).await?;
+
+    // The code is only used up by the recovery itself, which counts as its own attempt.
+    throttle.release(&app_state.db).await?;
+
+    Ok(())
+}
+
+/// Sets a new game with a recovery code. Two-factor login stays on, so it also needs a code from
+/// the authenticator app of users that turned it on.
+#[server]
+async fn recover_account(
+    name: String,
+    code: String,
+    password: Vec<(San, Fen)>,
+    totp: Option<String>,
+) -> Result<(), Error> {
+    use crate::app::login::record_failed_login;
+    use crate::app::register::check_new_password;
+    use crate::types::client::client_ip;
+    use crate::types::password::*;
+    use crate::types::throttle::LoginThrottle;
+    use crate::types::totp::verify_code;
+    use crate::types::username::lookup_username;
+    use crate::types::{record_auth_event, AppState, AuthEventKind, Session};
+    let app_state = expect_context::<AppState>();
+
+    let name = lookup_username(&name);
+
+    // Unknown names get the normal start, the code check below turns them away.
+    let user = sqlx::query!(
+        "SELECT variant, start_fen FROM users WHERE LOWER(username) = LOWER($1)",
+        name
+    )
+    .fetch_optional(&app_state.db.pool)
+    .await?;
+    let start = match user {
+        Some(user) => StartPosition::parse(&user.variant, user.start_fen.as_deref())?,
+        None => StartPosition::default(),
+    };
+
+    let moves = check_new_password(&app_state.password_policy, &start, &password)?;
+
+    let throttle = LoginThrottle::new(&name, client_ip());
+    let attempt = throttle.check(&app_state.db).await?;
+
+    let user_id = find_recovery_user(&app_state, &attempt, &name, &code).await?;
+
+    let user = sqlx::query!(
+        "SELECT totp_secret, totp_enabled, totp_last_step FROM users WHERE id = $1",
+        user_id
+    )
+    .fetch_one(&app_state.db.pool)
+    .await?;
+
+    let step = match (user.totp_enabled, &user.totp_secret) {
+        (true, Some(secret)) => {
+            let Some(totp) = totp else {
+                // The recovery code was right, asking for the authenticator code isn't a failed attempt.
+                throttle.release(&app_state.db).await?;
+                return Err(Error::SecondFactorRequired);
+            };
+            let Some(step) = verify_code(secret, &totp, user.totp_last_step) else {
+                let kind = AuthEventKind::WrongSecondFactor;
+                record_failed_login(&app_state, &attempt, Some(&user_id), &name, kind).await?;
+                return Err(Error::WrongSecondFactor);
+            };
+            Some(step)
+        }
+        _ => None,
+    };
+
+    let password_hash = hash_game(canonical_game(&start, &moves), app_state.pepper.clone()).await?;
+
+    let mut transaction = app_state.db.pool.begin().await?;
+
+    // Checked again while using it up, in case a parallel recovery got to the code first.
+    sqlx::query!(
+        r#"
+        UPDATE recovery_codes SET used_at = NOW()
+        WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+        RETURNING id
+        "#,
+        user_id,
+        hash_recovery_code(&code)
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or(Error::InvalidRecoveryCode)?;
+
+    sqlx::query!(
+        r#"
+        UPDATE users SET password_hash = $1, hash_version = $2, password = NULL, salt = NULL,
+            totp_last_step = COALESCE($3, totp_last_step)
+        WHERE id = $4
+        "#,
+        password_hash,
+        HASH_VERSION,
+        step,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    sqlx::query!("DELETE FROM sessions WHERE user_id = $1", user_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    throttle.reset(&app_state.db).await?;
+
+    Session::start(&app_state, &user_id).await?;
+    record_auth_event(
+        &app_state.db,
+        Some(&user_id),
+        &name,
+        AuthEventKind::AccountRecovered,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the id of the user if the code is one of their unused ones. Failures are logged and
+/// counted like failed logins.
+#[cfg(feature = "ssr")]
+async fn find_recovery_user(
+    app_state: &crate::types::AppState,
+    attempt: &crate::types::throttle::Attempt,
+    name: &str,
+    code: &str,
+) -> Result<String, Error> {
+    use crate::app::login::record_failed_login;
+    use crate::types::AuthEventKind;
+
+    let user = sqlx::query!(
+        "SELECT id FROM users WHERE LOWER(username) = LOWER($1)",
+        name
+    )
+    .fetch_optional(&app_state.db.pool)
+    .await?;
+
+    let Some(user) = user else {
+        record_failed_login(app_state, attempt, None, name, AuthEventKind::UnknownUser).await?;
+        return Err(Error::InvalidRecoveryCode);
+    };
+
+    let valid = sqlx::query_scalar!(
+        "SELECT id FROM recovery_codes WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL",
+        user.id,
+        hash_recovery_code(code)
+    )
+    .fetch_optional(&app_state.db.pool)
+    .await?
+    .is_some();
+
+    if !valid {
+        let kind = AuthEventKind::WrongRecoveryCode;
+        record_failed_login(app_state, attempt, Some(&user.id), name, kind).await?;
+        return Err(Error::InvalidRecoveryCode);
+    }
+
+    Ok(user.id)
+}
+
+/// Codes are random enough that a plain hash is fine, the throttle takes care of guessing.
+#[cfg(feature = "ssr")]
+fn hash_recovery_code(code: &str) -> Vec<u8> {
+    use sha2::{Digest, Sha256};
+
+    let normalized = code
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect::<String>();
+
+    Sha256::digest(normalized.as_bytes()).to_vec()
+}
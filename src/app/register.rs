@@ -1,36 +1,33 @@
-use crate::app::chess::ChessBoard;
-use gloo::storage::Storage;
 use leptos::either::{Either, EitherOf4};
-use leptos::logging::*;
 use leptos::prelude::*;
 use leptos_router::hooks::use_navigate;
 use leptos_router::NavigateOptions;
-use sha2::Digest;
-use sha2::Sha256;
 use shakmaty::fen::*;
 use shakmaty::san::*;
-use shakmaty::EnPassantMode;
-use shakmaty::KnownOutcome;
-use shakmaty::Position;
 
 use crate::app::game_modal::*;
+use crate::app::password::*;
+use crate::app::recovery::RecoveryCodes;
 
+use crate::types::start::StartPosition;
+use crate::types::username::normalize_username;
 use crate::types::Error;
 
-const MIN_PASSWORD_LENGTH: usize = 4;
-
 #[derive(Clone, Debug, PartialEq)]
 enum State {
     Username,
     Password {
         user_name: String,
+        start: StartPosition,
     },
     PasswordConfirm {
         user_name: String,
+        start: StartPosition,
         first_attempt: Vec<(San, Fen)>,
     },
     Done {
         user_name: String,
+        start: StartPosition,
         password: Vec<(San, Fen)>,
     },
 }
@@ -42,46 +39,55 @@ pub fn RegisterPage() -> impl IntoView {
     let current_view = move || match state.get() {
         State::Username => {
             let (user_name, set_username) = signal(String::new());
+            let start = RwSignal::new(Ok(StartPosition::default()));
 
-            let taken = Resource::new(
+            // The rules are checked right here, only whether the name is free needs the server.
+            let available = Resource::new(
                 move || user_name.get(),
                 |name| async move {
-                    if name.is_empty() {
-                        Ok(false)
-                    } else {
-                        is_username_taken(name).await
+                    let name = normalize_username(&name)?;
+                    match is_username_taken(name.clone()).await? {
+                        true => Err(Error::InvalidUsername("Username is taken".to_string())),
+                        false => Ok(name),
                     }
                 },
             );
 
             let suspense = move || {
                 Suspend::new(async move {
-                    let taken = taken.await.unwrap_or(false);
+                    let available = available.await;
+                    let taken = available.is_err();
+                    let blocked = move || taken || start.read().is_err();
+
+                    let problem = match &available {
+                        Err(e) if !user_name.read_untracked().is_empty() => Some(e.to_string()),
+                        _ => None,
+                    };
 
                     let on_click = move |_| {
-                        if taken {
+                        let Ok(start) = start.get() else {
                             return;
-                        }
+                        };
+                        let Ok(user_name) = available.clone() else {
+                            return;
+                        };
 
-                        set_state.set(State::Password {
-                            user_name: user_name.get(),
-                        })
+                        set_state.set(State::Password { user_name, start })
                     };
 
                     view! {
-                        {if taken {
-                            Either::Left(
-                                view! { <span class="text-red-500">"Username is taken"</span> },
-                            )
-                        } else {
-                            Either::Right(())
+                        {match problem {
+                            Some(problem) => {
+                                Either::Left(view! { <span class="text-red-500">{problem}</span> })
+                            }
+                            None => Either::Right(()),
                         }}
                         <button
                             on:click=on_click
                             class="p-4 w-full text-2xl"
-                            class:button-primary=!taken
-                            class:button-secondary=taken
-                            class:bg-secondary-hover=taken
+                            class:button-primary=move || !blocked()
+                            class:button-secondary=blocked
+                            class:bg-secondary-hover=blocked
                         >
                             "Continue"
                         </button>
@@ -98,186 +104,72 @@ pub fn RegisterPage() -> impl IntoView {
                         type="text"
                         bind:value=(user_name, set_username)
                     />
+                    <ChooseStart start />
                     <Transition fallback=move || {
                         view! { <div>"Checking..."</div> }
                     }>{suspense}</Transition>
                 </div>
             })
         }
-        State::Password { user_name } => {
-            let notation: RwSignal<Vec<(San, Fen)>> = RwSignal::new(vec![]);
-
-            let over_min_moves = move || notation.read().len() >= MIN_PASSWORD_LENGTH;
-
-            Effect::new(move |_| {
-                let s = notation
-                    .get()
-                    .into_iter()
-                    .map(|e| format!("san {} fen {}", e.0, e.1))
-                    .collect::<Vec<String>>()
-                    .join(" \n");
-                log!("{s}");
-            });
-
-            let (ended, set_ended) = signal(Option::<KnownOutcome>::None);
-
-            let on_finished = move |o: KnownOutcome| {
-                log!("ended {o:?}");
-                set_ended.set(Some(o));
-            };
-
+        State::Password { user_name, start } => {
             let on_continue = {
                 let user_name = user_name.clone();
-                move |_| {
-                    if !over_min_moves() {
-                        return;
-                    }
-
+                let start = start.clone();
+                move |first_attempt| {
                     set_state.set(State::PasswordConfirm {
                         user_name: user_name.clone(),
-                        first_attempt: notation.get(),
+                        start: start.clone(),
+                        first_attempt,
                     });
                 }
             };
 
             EitherOf4::B(view! {
-                <div class="flex flex-col gap-2.5 justify-start items-center w-full h-full">
-                    <div class="flex flex-col justify-start items-start w-full text-2xl h-fit">
-                        "Hi " {user_name}
-                        <span class="font-sans font-light">
-                            "Let's make a password! Play a game of chess with yourself until the game is over! Remember the game well!"
-                        </span>
-                    </div>
-                    <div class="flex flex-row justify-around items-center w-full h-full">
-                        <div class="flex flex-col justify-center items-center">
-                            <ChessBoard on_finished notation />
-                            <GameEndModal ended on_continue=on_continue.clone() />
-                        </div>
-
-                        <button
-                            on:click=on_continue
-                            class="p-10 text-2xl"
-                            class:button-primary=over_min_moves
-                            class:button-secondary=move || !over_min_moves()
-                            class:bg-secondary-hover=move || !over_min_moves()
-                        >
-                            {move || {
-                                if over_min_moves() {
-                                    Either::Left("Continue")
-                                } else {
-                                    Either::Right(
-                                        format!("Play at least {MIN_PASSWORD_LENGTH} moves"),
-                                    )
-                                }
-                            }}
-                        </button>
-                    </div>
-                </div>
+                <PlayPassword
+                    user_name
+                    start
+                    check_strength=true
+                    allow_pgn=true
+                    intro="Let's make a password! Play a game of chess with yourself until the game is over! Remember the game well!"
+                    on_continue
+                />
             })
         }
         State::PasswordConfirm {
             user_name,
+            start,
             first_attempt,
         } => {
-            let notation: RwSignal<Vec<(San, Fen)>> = RwSignal::new(vec![]);
-
-            Effect::new(move |_| {
-                let s = notation
-                    .get()
-                    .into_iter()
-                    .map(|e| format!("san {} fen {}", e.0, e.1))
-                    .collect::<Vec<String>>()
-                    .join(" \n");
-                log!("{s}");
-            });
-
-            let matches = {
-                let first = first_attempt.clone();
-                move || {
-                    let notation = notation.get();
-                    let last_element = match notation.last() {
-                        Some(e) => e,
-                        None => return true,
-                    };
-
-                    let first_element = match first.get(notation.len() - 1) {
-                        Some(e) => e,
-                        None => return false,
-                    };
-
-                    last_element == first_element
-                }
-            };
-
-            let completed = {
-                let first_attempt = first_attempt.clone();
-                let matches = matches.clone();
-                move || notation.read().len() == first_attempt.len() && matches()
-            };
-
-            let (ended, set_ended) = signal(Option::<KnownOutcome>::None);
-
-            let on_finished = move |o: KnownOutcome| {
-                log!("ended {o:?}");
-                set_ended.set(Some(o));
-            };
-
-            let on_continue = {
+            let on_confirmed = {
                 let user_name = user_name.clone();
-                let first_attempt = first_attempt.clone();
-
-                move |_| {
-                    if first_attempt == notation.get() {
-                        set_state.set(State::Done {
-                            user_name: user_name.clone(),
-                            password: notation.get(),
-                        });
-                    } else {
-                        error!("Passwords do not match");
-                    }
+                let start = start.clone();
+                move |password| {
+                    set_state.set(State::Done {
+                        user_name: user_name.clone(),
+                        start: start.clone(),
+                        password,
+                    });
                 }
             };
 
-            let on_restart = move |_| {
-                set_state.set(state.get());
-            };
-
             EitherOf4::C(view! {
-                <div class="flex flex-col gap-2.5 justify-start items-center w-full h-full">
-                    <div class="flex flex-col justify-start items-start w-full text-2xl h-fit">
-                        "Hi " {user_name}
-                        <span class="font-sans font-light">
-                            "Now play the same game of chess again!"
-                        </span>
-                    </div>
-                    <div class="flex flex-row justify-around items-center w-full h-full">
-                        <div class="flex flex-col justify-center items-center w-full h-full">
-                            <ChessBoard on_finished notation />
-                            <GameEndModal ended on_continue=on_continue.clone() />
-                            <GameModal
-                                visible=Signal::derive(move || !matches())
-                                main_text="Move doesn't match"
-                                sub_text="Please try again"
-                                button_text="Retry"
-                                on_click=on_restart
-                            />
-                            <GameModal
-                                visible=Signal::derive(completed)
-                                main_text="Password Matches!!"
-                                button_text="Continue!"
-                                on_click=on_continue
-                            />
-                        </div>
-                    </div>
-                </div>
+                <ConfirmPassword
+                    user_name
+                    start
+                    first_attempt
+                    on_confirmed
+                    allow_pgn=true
+                />
             })
         }
         State::Done {
             user_name,
+            start,
             password,
         } => {
-            let result =
-                LocalResource::new(move || create_user(user_name.clone(), password.clone()));
+            let result = LocalResource::new(move || {
+                create_user(user_name.clone(), start.clone(), password.clone())
+            });
 
             let on_home = move |_| {
                 use_navigate()("/", NavigateOptions::default());
@@ -286,25 +178,25 @@ pub fn RegisterPage() -> impl IntoView {
             EitherOf4::D(view! {
                 <Suspense>
                     {move || Suspend::new(async move {
-                        let result = result.await;
-                        let (main_text, sub_text) = match &result {
-                            Ok(_) => ("User created".to_string(), "Yipeee 😁😁".to_string()),
-                            Err(e) => ("Something went wrong".to_string(), e.to_string()),
-                        };
-                        if let Ok(user_id) = &result {
-                            if let Err(e) = gloo::storage::LocalStorage::set("id", user_id) {
-                                error!("Could not store user id in local storage: {e}");
-                            }
-                        }
-
-                        view! {
-                            <GameModal
-                                visible=true
-                                main_text=main_text
-                                sub_text=sub_text
-                                button_text="Home!"
-                                on_click=on_home
-                            />
+                        match result.await {
+                            Ok(codes) => Either::Left(view! {
+                                <div class="flex flex-col gap-4 items-center">
+                                    <span class="text-5xl">"User created"</span>
+                                    <RecoveryCodes codes />
+                                    <button on:click=on_home class="p-4 w-full text-2xl button-primary">
+                                        "Home!"
+                                    </button>
+                                </div>
+                            }),
+                            Err(e) => Either::Right(view! {
+                                <GameModal
+                                    visible=true
+                                    main_text="Something went wrong"
+                                    sub_text=e.to_string()
+                                    button_text="Home!"
+                                    on_click=on_home
+                                />
+                            }),
                         }
                     })}
                 </Suspense>
@@ -313,7 +205,7 @@ pub fn RegisterPage() -> impl IntoView {
     };
 
     view! {
-        <div class="flex flex-col justify-center items-center p-5 w-full h-full">
+        <div class="flex overflow-y-auto flex-col justify-start items-center p-5 w-full h-full md:justify-center">
             {current_view}
         </div>
     }
@@ -324,9 +216,11 @@ async fn is_username_taken(name: String) -> Result<bool, Error> {
     use crate::types::AppState;
     let app_state = expect_context::<AppState>();
 
+    let name = normalize_username(&name)?;
+
     let rec = sqlx::query!(
         r#"
-        SELECT id FROM users WHERE username = $1
+        SELECT id FROM users WHERE LOWER(username) = LOWER($1)
         "#,
         name
     )
@@ -336,63 +230,105 @@ async fn is_username_taken(name: String) -> Result<bool, Error> {
     Ok(rec.is_some())
 }
 
+/// Creates the user with its first set of recovery codes, which are returned to show them once.
 #[server]
-async fn create_user(name: String, password: Vec<(San, Fen)>) -> Result<String, Error> {
-    use crate::types::AppState;
+async fn create_user(
+    name: String,
+    start: StartPosition,
+    password: Vec<(San, Fen)>,
+) -> Result<Vec<String>, Error> {
+    use crate::app::recovery::replace_recovery_codes;
+    use crate::types::password::*;
+    use crate::types::{record_auth_event, AppState, AuthEventKind, Session};
     let app_state = expect_context::<AppState>();
 
-    if !check_chess_moves(&password) {
-        return Err(Error::ImpossibleChessGame);
-    }
+    let name = normalize_username(&name)?;
+    let moves = check_new_password(&app_state.password_policy, &start, &password)?;
+    let password_hash = hash_game(canonical_game(&start, &moves), app_state.pepper.clone()).await?;
 
     let mut transaction = app_state.db.pool.begin().await?;
 
     let user_id = cuid2::cuid();
-    let salt = cuid2::CuidConstructor::new().with_length(5).create_id();
-
-    let fen_hashed_vec =
-        hash_fen_with_salt(password.into_iter().map(|(_san, fen)| fen).collect(), &salt);
 
     sqlx::query!(
         r#"
-        INSERT INTO users (id, username, password, salt) VALUES ($1, $2, $3, $4)
+        INSERT INTO users (id, username, password_hash, hash_version, variant, start_fen)
+        VALUES ($1, $2, $3, $4, $5, $6)
         "#,
         user_id,
         name,
-        &fen_hashed_vec,
-        salt
+        password_hash,
+        HASH_VERSION,
+        start.variant.uci(),
+        start.fen.map(|fen| fen.to_string())
     )
     .execute(&mut *transaction)
     .await?;
 
+    let codes = replace_recovery_codes(&mut transaction, &user_id).await?;
+
     transaction.commit().await?;
 
-    Ok(user_id)
+    record_auth_event(
+        &app_state.db,
+        Some(&user_id),
+        &name,
+        AuthEventKind::Registration,
+    )
+    .await?;
+
+    Session::start(&app_state, &user_id).await?;
+
+    Ok(codes)
+}
+
+/// Everything a new password has to pass before it gets hashed, returns the played moves.
+#[cfg(feature = "ssr")]
+pub fn check_new_password(
+    policy: &crate::types::policy::PasswordPolicy,
+    start: &StartPosition,
+    password: &[(San, Fen)],
+) -> Result<Vec<shakmaty::Move>, Error> {
+    use crate::types::strength::PasswordStrength;
+
+    let start = start.position()?;
+    let moves = check_chess_moves(start.clone(), password).ok_or(Error::ImpossibleChessGame)?;
+
+    policy.check(&start, password)?;
+
+    let sans = password.iter().map(|(san, _fen)| *san).collect::<Vec<_>>();
+    if !PasswordStrength::estimate(&start, &sans).is_acceptable() {
+        return Err(Error::WeakPassword);
+    }
+
+    Ok(moves)
 }
 
-fn check_chess_moves(moves: &[(San, Fen)]) -> bool {
-    let mut pos = shakmaty::Chess::default();
+/// Replays the game from `start` by the rules of its variant,
+/// and returns the played moves, or `None` if it isn't a legal game.
+#[cfg(feature = "ssr")]
+pub fn check_chess_moves(
+    start: shakmaty::variant::VariantPosition,
+    moves: &[(San, Fen)],
+) -> Option<Vec<shakmaty::Move>> {
+    use shakmaty::{EnPassantMode, Position};
+
+    let mut pos = start;
+    let mut played = Vec::with_capacity(moves.len());
     for (san, fen) in moves {
         let mv = match san.to_move(&pos) {
             Ok(mv) => mv,
-            Err(_) => return false,
+            Err(_) => return None,
         };
         pos = match pos.play(mv) {
             Ok(p) => p,
-            Err(_) => return false,
+            Err(_) => return None,
         };
         let current_fen = Fen::from_position(&pos, EnPassantMode::Legal);
         if &current_fen != fen {
-            return false;
+            return None;
         }
+        played.push(mv);
     }
-    true
-}
-
-pub fn hash_fen_with_salt(fen: Vec<Fen>, salt: &str) -> Vec<Vec<u8>> {
-    fen.into_iter()
-        .map(|f| f.to_string() + salt)
-        .map(|s| Sha256::digest(s.as_bytes()))
-        .map(|d| d.to_vec())
-        .collect::<Vec<_>>()
+    Some(played)
 }
@@ -1,6 +1,4 @@
-use leptos::logging::*;
 use leptos::prelude::*;
-use leptos::tachys::view;
 use leptos_router::hooks::use_navigate;
 use leptos_router::NavigateOptions;
 
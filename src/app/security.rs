@@ -0,0 +1,86 @@
+use leptos::either::EitherOf3;
+use leptos::prelude::*;
+
+use crate::types::{AuthEvent, Error};
+
+/// How many of the latest events the security history shows.
+#[cfg(feature = "ssr")]
+const SECURITY_HISTORY_LENGTH: i64 = 50;
+
+#[component]
+pub fn SecurityHistoryPage() -> impl IntoView {
+    let events = Resource::new(|| (), |_| security_history());
+
+    let suspense = move || {
+        Suspend::new(async move {
+            match events.await {
+                Ok(events) if events.is_empty() => {
+                    EitherOf3::A(view! { <div>"Nothing happened yet"</div> })
+                }
+                Ok(events) => EitherOf3::B(
+                    events
+                        .into_iter()
+                        .map(|event| view! { <SecurityEvent event /> })
+                        .collect_view(),
+                ),
+                Err(e) => EitherOf3::C(
+                    view! { <div class="text-red-700">"Error loading the history: " {e.to_string()}</div> },
+                ),
+            }
+        })
+    };
+
+    view! {
+        <div class="flex overflow-scroll flex-col gap-4 justify-start p-4 w-full h-full">
+            <span class="w-full text-3xl h-fit">"Security history"</span>
+            <span class="font-sans text-lg font-light">
+                "Recent logins and failed attempts on your account. If you don't recognise one, change your password."
+            </span>
+            <Transition fallback=move || view! { <div>"Loading..."</div> }>{suspense}</Transition>
+        </div>
+    }
+}
+
+#[component]
+fn SecurityEvent(event: AuthEvent) -> impl IntoView {
+    let failure = event.kind.is_failure();
+
+    view! {
+        <div class="flex flex-col items-start p-4 w-full rounded-2xl bg-secondary">
+            <span class="text-xl" class:text-red-500=failure>
+                {event.kind.label()}
+            </span>
+            <span class="font-sans text-lg font-light">
+                {event.created_at.format("%Y-%m-%d %H:%M UTC").to_string()} " from "
+                {event.ip.unwrap_or_else(|| "an unknown address".to_string())}
+            </span>
+            <span class="font-sans text-sm font-light">
+                {event.user_agent.unwrap_or_else(|| "Unknown browser".to_string())}
+            </span>
+        </div>
+    }
+}
+
+#[server]
+async fn security_history() -> Result<Vec<AuthEvent>, Error> {
+    use crate::types::{AppState, AuthEventKind, Session};
+    let app_state = expect_context::<AppState>();
+
+    let session = Session::require(&app_state).await?;
+
+    let events = sqlx::query_as!(
+        AuthEvent,
+        r#"
+        SELECT kind as "kind: AuthEventKind", ip, user_agent, created_at FROM auth_events
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        LIMIT $2
+        "#,
+        session.user_id,
+        SECURITY_HISTORY_LENGTH
+    )
+    .fetch_all(&app_state.db.pool)
+    .await?;
+
+    Ok(events)
+}
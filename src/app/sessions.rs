@@ -0,0 +1,177 @@
+use leptos::either::{Either, EitherOf3};
+use leptos::logging::*;
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+use crate::types::{Error, SessionInfo};
+
+#[component]
+pub fn SessionsPage() -> impl IntoView {
+    let sessions = Resource::new(|| (), |_| list_sessions());
+
+    let on_revoke_all = move |_| {
+        spawn_local(async move {
+            if let Err(e) = revoke_all_sessions().await {
+                error!("Error revoking sessions: {e}");
+            }
+            reload();
+        })
+    };
+
+    let suspense = move || {
+        Suspend::new(async move {
+            match sessions.await {
+                Ok(list) if list.is_empty() => EitherOf3::A(view! { <div>"Not logged in"</div> }),
+                Ok(list) => EitherOf3::B(
+                    list.into_iter()
+                        .map(|session| view! { <SessionEntry session sessions /> })
+                        .collect_view(),
+                ),
+                Err(e) => EitherOf3::C(
+                    view! { <div class="text-red-700">"Error loading sessions: " {e.to_string()}</div> },
+                ),
+            }
+        })
+    };
+
+    view! {
+        <div class="flex overflow-scroll flex-col gap-4 justify-start p-4 w-full h-full">
+            <span class="w-full text-3xl h-fit">"Active sessions"</span>
+            <span class="font-sans text-lg font-light">
+                "Everywhere you are logged in. Log out anything you don't recognise."
+            </span>
+            <Transition fallback=move || view! { <div>"Loading..."</div> }>{suspense}</Transition>
+            <button on:click=on_revoke_all class="p-4 text-2xl button-primary w-fit">
+                "Log out everywhere"
+            </button>
+        </div>
+    }
+}
+
+#[component]
+fn SessionEntry(
+    session: SessionInfo,
+    sessions: Resource<Result<Vec<SessionInfo>, Error>>,
+) -> impl IntoView {
+    let current = session.current;
+    let id = session.id.clone();
+
+    let on_revoke = move |_| {
+        let id = id.clone();
+        spawn_local(async move {
+            if let Err(e) = revoke_session(id).await {
+                error!("Error revoking session: {e}");
+            }
+            match current {
+                true => reload(),
+                false => sessions.refetch(),
+            }
+        })
+    };
+
+    let format_time =
+        |time: chrono::DateTime<chrono::Utc>| time.format("%Y-%m-%d %H:%M UTC").to_string();
+
+    view! {
+        <div class="flex flex-row justify-between items-center p-4 w-full rounded-2xl bg-secondary">
+            <div class="flex flex-col items-start">
+                <span class="text-xl">
+                    {session.user_agent.unwrap_or_else(|| "Unknown browser".to_string())}
+                </span>
+                <span class="font-sans text-lg font-light">
+                    "Logged in " {format_time(session.created_at)} ", last seen "
+                    {format_time(session.last_seen_at)}
+                </span>
+                {match current {
+                    true => Either::Left(view! { <span class="text-lg font-bold">"This browser"</span> }),
+                    false => Either::Right(()),
+                }}
+            </div>
+            <button on:click=on_revoke class="p-2 text-lg button-secondary">
+                "Log out"
+            </button>
+        </div>
+    }
+}
+
+/// The sidebar and every other page still think we are logged in, so start over.
+fn reload() {
+    if let Some(w) = leptos::web_sys::window() {
+        if let Err(e) = w.location().reload() {
+            error!("Error reloading page after logout: {e:?}");
+        }
+    }
+}
+
+#[server]
+async fn list_sessions() -> Result<Vec<SessionInfo>, Error> {
+    use crate::types::{AppState, Session};
+    let app_state = expect_context::<AppState>();
+
+    let session = match Session::current(&app_state).await? {
+        Some(session) => session,
+        None => return Ok(vec![]),
+    };
+
+    let sessions = sqlx::query!(
+        r#"
+        SELECT id, created_at, last_seen_at, user_agent FROM sessions
+        WHERE user_id = $1 AND expires_at > NOW()
+        ORDER BY last_seen_at DESC
+        "#,
+        session.user_id
+    )
+    .fetch_all(&app_state.db.pool)
+    .await?
+    .into_iter()
+    .map(|row| SessionInfo {
+        current: row.id == session.id,
+        id: row.id,
+        created_at: row.created_at,
+        last_seen_at: row.last_seen_at,
+        user_agent: row.user_agent,
+    })
+    .collect();
+
+    Ok(sessions)
+}
+
+#[server]
+async fn revoke_session(id: String) -> Result<(), Error> {
+    use crate::types::{AppState, Session};
+    let app_state = expect_context::<AppState>();
+
+    let session = Session::require(&app_state).await?;
+
+    if session.id == id {
+        return session.revoke(&app_state).await;
+    }
+
+    sqlx::query!(
+        "DELETE FROM sessions WHERE id = $1 AND user_id = $2",
+        id,
+        session.user_id
+    )
+    .execute(&app_state.db.pool)
+    .await?;
+
+    Ok(())
+}
+
+#[server]
+async fn revoke_all_sessions() -> Result<(), Error> {
+    use crate::types::{AppState, Session};
+    let app_state = expect_context::<AppState>();
+
+    let session = Session::require(&app_state).await?;
+
+    sqlx::query!(
+        "DELETE FROM sessions WHERE user_id = $1 AND id <> $2",
+        session.user_id,
+        session.id
+    )
+    .execute(&app_state.db.pool)
+    .await?;
+
+    session.revoke(&app_state).await
+}
@@ -0,0 +1,358 @@
+use leptos::either::{EitherOf3, EitherOf5};
+use leptos::prelude::*;
+use leptos_router::hooks::use_navigate;
+use leptos_router::NavigateOptions;
+use shakmaty::fen::*;
+use shakmaty::san::*;
+
+use crate::app::game_modal::*;
+use crate::app::login::get_start_position;
+use crate::app::password::*;
+use crate::app::sidebar::get_user_name;
+use crate::types::start::StartPosition;
+use crate::types::Error;
+
+#[component]
+pub fn SettingsPage() -> impl IntoView {
+    let user_name = Resource::new(|| (), |_| get_user_name());
+
+    Effect::new(move || {
+        if let Some(Ok(None)) = user_name.get() {
+            use_navigate()("/login-register", NavigateOptions::default());
+        }
+    });
+
+    let suspense = move || {
+        Suspend::new(async move {
+            match user_name.await {
+                Ok(Some(name)) => EitherOf3::A(view! {
+                    <span class="font-sans text-xl font-light">"Logged in as " {name}</span>
+                    <SettingsEntry
+                        title="Change password"
+                        description="Replay your current game, then play a new one"
+                        href="/settings/password"
+                    />
+                    <SettingsEntry
+                        title="Recovery codes"
+                        description="Get new codes for when you forget your game"
+                        href="/settings/recovery-codes"
+                    />
+                    <SettingsEntry
+                        title="Two-factor login"
+                        description="Also ask for a code from an authenticator app"
+                        href="/settings/two-factor"
+                    />
+                    <SettingsEntry
+                        title="Active sessions"
+                        description="See where you are logged in and log out other browsers"
+                        href="/settings/sessions"
+                    />
+                    <SettingsEntry
+                        title="Security history"
+                        description="Recent logins and failed attempts on your account"
+                        href="/settings/security"
+                    />
+                    <SettingsEntry
+                        title="Export my data"
+                        description="Download your profile and all of your votes"
+                        href="/settings/export"
+                    />
+                    <SettingsEntry
+                        title="Delete account"
+                        description="Leave for good, with or without your votes"
+                        href="/settings/delete-account"
+                    />
+                }),
+                Ok(None) => EitherOf3::B(view! { <div>"Not logged in"</div> }),
+                Err(e) => EitherOf3::C(
+                    view! { <div class="text-red-700">"Error loading settings: " {e.to_string()}</div> },
+                ),
+            }
+        })
+    };
+
+    view! {
+        <div class="flex overflow-scroll flex-col gap-4 justify-start p-4 w-full h-full">
+            <span class="w-full text-3xl h-fit">"Settings"</span>
+            <Transition fallback=move || view! { <div>"Loading..."</div> }>{suspense}</Transition>
+        </div>
+    }
+}
+
+#[component]
+fn SettingsEntry(
+    #[prop(into)] title: String,
+    #[prop(into)] description: String,
+    #[prop(into)] href: String,
+) -> impl IntoView {
+    let on_click = move |_| use_navigate()(&href, NavigateOptions::default());
+
+    view! {
+        <button
+            on:click=on_click
+            class="flex flex-col items-start p-4 w-full text-left rounded-2xl bg-secondary hover:bg-secondary-hover"
+        >
+            <span class="text-2xl">{title}</span>
+            <span class="font-sans text-lg font-light">{description}</span>
+        </button>
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum State {
+    CurrentPassword,
+    Checking {
+        current: Vec<(San, Fen)>,
+    },
+    Password {
+        current: Vec<(San, Fen)>,
+    },
+    PasswordConfirm {
+        current: Vec<(San, Fen)>,
+        first_attempt: Vec<(San, Fen)>,
+    },
+    Done {
+        current: Vec<(San, Fen)>,
+        password: Vec<(San, Fen)>,
+    },
+}
+
+#[component]
+pub fn ChangePasswordPage() -> impl IntoView {
+    let user = Resource::new(
+        || (),
+        |_| async move {
+            match get_user_name().await? {
+                Some(name) => {
+                    let start = get_start_position(name.clone()).await?;
+                    Ok(Some((name, start)))
+                }
+                None => Ok::<_, Error>(None),
+            }
+        },
+    );
+
+    Effect::new(move || {
+        if let Some(Ok(None)) = user.get() {
+            use_navigate()("/login-register", NavigateOptions::default());
+        }
+    });
+
+    let suspense = move || {
+        Suspend::new(async move {
+            match user.await {
+                Ok(Some((user_name, start))) => {
+                    EitherOf3::A(view! { <ChangePassword user_name start /> })
+                }
+                Ok(None) => EitherOf3::B(view! { <div>"Not logged in"</div> }),
+                Err(e) => EitherOf3::C(
+                    view! { <div class="text-red-700">"Error loading username: " {e.to_string()}</div> },
+                ),
+            }
+        })
+    };
+
+    view! {
+        <div class="flex overflow-y-auto flex-col justify-start items-center p-5 w-full h-full md:justify-center">
+            <Transition fallback=move || view! { <div>"Loading..."</div> }>{suspense}</Transition>
+        </div>
+    }
+}
+
+#[component]
+fn ChangePassword(user_name: String, start: StartPosition) -> impl IntoView {
+    let (state, set_state) = signal(State::CurrentPassword);
+
+    let current_view = move || match state.get() {
+        State::CurrentPassword => {
+            let on_continue = move |current| set_state.set(State::Checking { current });
+
+            EitherOf5::A(view! {
+                <PlayPassword
+                    user_name=user_name.clone()
+                    start=start.clone()
+                    intro="First replay the game that is your current password."
+                    on_continue
+                />
+            })
+        }
+        State::Checking { current } => {
+            let result = {
+                let current = current.clone();
+                LocalResource::new(move || verify_password(current.clone()))
+            };
+
+            let suspense = move || {
+                let current = current.clone();
+                Suspend::new(async move {
+                    let result = result.await;
+
+                    if result.is_ok() {
+                        set_state.set(State::Password { current });
+                    }
+
+                    let (main_text, sub_text) = match &result {
+                        Ok(_) => ("Correct".to_string(), String::new()),
+                        Err(Error::WrongPassword) => (
+                            "Wrong password".to_string(),
+                            "That is not your current game".to_string(),
+                        ),
+                        Err(e) => ("Something went wrong".to_string(), e.to_string()),
+                    };
+
+                    let on_click = move |_| set_state.set(State::CurrentPassword);
+
+                    view! {
+                        <GameModal
+                            visible=result.is_err()
+                            main_text
+                            sub_text
+                            button_text="Try again"
+                            on_click
+                        />
+                    }
+                })
+            };
+
+            EitherOf5::B(view! { <Suspense>{suspense}</Suspense> })
+        }
+        State::Password { current } => {
+            let on_continue = move |first_attempt| {
+                set_state.set(State::PasswordConfirm {
+                    current: current.clone(),
+                    first_attempt,
+                })
+            };
+
+            EitherOf5::C(view! {
+                <PlayPassword
+                    user_name=user_name.clone()
+                    start=start.clone()
+                    intro="Now play the game that will be your new password! Remember it well!"
+                    check_strength=true
+                    on_continue
+                />
+            })
+        }
+        State::PasswordConfirm {
+            current,
+            first_attempt,
+        } => {
+            let on_confirmed = move |password| {
+                set_state.set(State::Done {
+                    current: current.clone(),
+                    password,
+                })
+            };
+
+            EitherOf5::D(view! {
+                <ConfirmPassword
+                    user_name=user_name.clone()
+                    start=start.clone()
+                    first_attempt
+                    on_confirmed
+                />
+            })
+        }
+        State::Done { current, password } => {
+            let result =
+                LocalResource::new(move || change_password(current.clone(), password.clone()));
+
+            let on_click = move |_| use_navigate()("/settings", NavigateOptions::default());
+
+            EitherOf5::E(view! {
+                <Suspense>
+                    {move || Suspend::new(async move {
+                        let (main_text, sub_text) = match result.await {
+                            Ok(_) => (
+                                "Password changed".to_string(),
+                                "You were logged out everywhere else".to_string(),
+                            ),
+                            Err(e) => ("Something went wrong".to_string(), e.to_string()),
+                        };
+
+                        view! {
+                            <GameModal
+                                visible=true
+                                main_text
+                                sub_text
+                                button_text="Back to settings"
+                                on_click
+                            />
+                        }
+                    })}
+                </Suspense>
+            })
+        }
+    };
+
+    view! { {current_view} }
+}
+
+#[server]
+async fn verify_password(password: Vec<(San, Fen)>) -> Result<(), Error> {
+    use crate::app::login::{reauthenticate, SecondFactor};
+    use crate::types::AppState;
+    let app_state = expect_context::<AppState>();
+
+    reauthenticate(&app_state, &password, SecondFactor::Skip).await?;
+
+    Ok(())
+}
+
+#[server]
+async fn change_password(current: Vec<(San, Fen)>, password: Vec<(San, Fen)>) -> Result<(), Error> {
+    use crate::app::login::{reauthenticate, SecondFactor};
+    use crate::app::register::check_new_password;
+    use crate::types::password::*;
+    use crate::types::{record_auth_event, AppState, AuthEventKind};
+    let app_state = expect_context::<AppState>();
+
+    let session = reauthenticate(&app_state, &current, SecondFactor::Skip).await?;
+
+    let user = sqlx::query!(
+        "SELECT username, variant, start_fen FROM users WHERE id = $1",
+        session.user_id
+    )
+    .fetch_one(&app_state.db.pool)
+    .await?;
+    let start = StartPosition::parse(&user.variant, user.start_fen.as_deref())?;
+
+    let moves = check_new_password(&app_state.password_policy, &start, &password)?;
+
+    let password_hash = hash_game(canonical_game(&start, &moves), app_state.pepper.clone()).await?;
+
+    let mut transaction = app_state.db.pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE users SET password_hash = $1, hash_version = $2, password = NULL, salt = NULL
+        WHERE id = $3
+        "#,
+        password_hash,
+        HASH_VERSION,
+        session.user_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM sessions WHERE user_id = $1 AND id <> $2",
+        session.user_id,
+        session.id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    record_auth_event(
+        &app_state.db,
+        Some(&session.user_id),
+        &user.username,
+        AuthEventKind::PasswordChanged,
+    )
+    .await?;
+
+    Ok(())
+}
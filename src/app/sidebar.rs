@@ -1,82 +1,77 @@
-use leptos::either::Either;
-use leptos::ev::error;
+use leptos::either::EitherOf3;
 use leptos::logging::*;
 use leptos::prelude::*;
+use leptos::task::spawn_local;
 use leptos_router::components::{Outlet, A};
 use leptos_router::hooks::use_navigate;
 use leptos_router::NavigateOptions;
 
+use crate::types::Error;
+
 #[component]
 pub fn Sidebar() -> impl IntoView {
-    let (user_id, set_user_id) = signal(None::<String>);
-
-    Effect::new(move || {
-        use gloo::storage::{LocalStorage, Storage};
-
-        let res = LocalStorage::get("id");
-
-        if let Err(e) = &res {
-            log!("No user_id found in local storage: {e:?}");
-        }
-
-        set_user_id.set(res.ok());
-    });
-
-    let bottom = move || match user_id.get() {
-        Some(user_id) => {
-            let name = OnceResource::new(get_user_name(user_id));
-            let on_logout = move |_| {
-                use gloo::storage::{LocalStorage, Storage};
+    let user_name = Resource::new(|| (), |_| get_user_name());
 
-                LocalStorage::delete("id");
+    let on_logout = move |_| {
+        spawn_local(async move {
+            if let Err(e) = logout().await {
+                error!("Error logging out: {e}");
+            }
 
-                if let Some(w) = leptos::web_sys::window() {
-                    if let Err(e) = w.location().reload() {
-                        error!("Error reloading page after logout: {e:?}");
-                    }
+            if let Some(w) = leptos::web_sys::window() {
+                if let Err(e) = w.location().reload() {
+                    error!("Error reloading page after logout: {e:?}");
                 }
-            };
-            let suspense = move || {
-                Suspend::new(async move {
-                    match name.await {
-                        Ok(name) => Either::Left(view! {
-                            <div class="flex flex-col items-start p-4">
-                                <span class="font-sans text-lg font-light">"Logged in as"</span>
-                                <span class="text-2xl font-bold">{name}</span>
-                            </div>
-                        }),
-                        Err(e) => Either::Right(
-                            view! { <div class="text-red-700">"Error loading username: " {e.to_string()}</div> },
-                        ),
-                    }
-                })
-            };
-            Either::Left(view! {
-                {suspense}
-                <Section on:click=on_logout text="Log Out" image_src="/logout-icon.png" />
-            })
-        }
-        None => Either::Right(view! {
-            <button
-                on:click=move |_| { use_navigate()("/register", NavigateOptions::default()) }
-                class="p-2 mx-2 w-auto text-lg button-primary"
-            >
-                "Sign Up"
-            </button>
-            <button
-                on:click=move |_| { use_navigate()("/login", NavigateOptions::default()) }
-                class="p-2 mx-2 w-auto text-lg button-secondary bg-background hover:bg-secondary-hover"
-            >
-                "Log In"
-            </button>
-        }),
+            }
+        })
+    };
+
+    let bottom = move || {
+        Suspend::new(async move {
+            match user_name.await {
+                Ok(Some(name)) => EitherOf3::A(view! {
+                    <A href="/settings">
+                        <div class="flex flex-col items-start p-4 hover:bg-secondary-hover">
+                            <span class="hidden font-sans text-lg font-light md:inline">
+                                "Logged in as"
+                            </span>
+                            <span class="max-w-24 truncate md:max-w-none md:text-2xl font-bold">
+                                {name}
+                            </span>
+                        </div>
+                    </A>
+                    <Section on:click=on_logout text="Log Out" image_src="/logout-icon.png" />
+                }),
+                Ok(None) => EitherOf3::B(view! {
+                    <button
+                        on:click=move |_| { use_navigate()("/register", NavigateOptions::default()) }
+                        class="p-2 mx-2 w-auto md:text-lg button-primary"
+                    >
+                        "Sign Up"
+                    </button>
+                    <button
+                        on:click=move |_| { use_navigate()("/login", NavigateOptions::default()) }
+                        class="p-2 mx-2 w-auto md:text-lg button-secondary bg-background hover:bg-secondary-hover"
+                    >
+                        "Log In"
+                    </button>
+                }),
+                Err(e) => EitherOf3::C(
+                    view! { <div class="text-red-700">"Error loading username: " {e.to_string()}</div> },
+                ),
+            }
+        })
     };
 
+    // A column on the left, on narrow screens a bar along the bottom instead.
     view! {
-        <div class="flex flex-row w-full h-full">
-            <div class="flex flex-col justify-between w-auto h-full bg-secondary">
-                <div class="flex flex-col w-full h-fit">
-                    <a href="/" class="p-2 text-3xl">
+        <div class="flex flex-col-reverse w-full h-full md:flex-row">
+            <nav
+                aria-label="Main"
+                class="flex flex-row justify-between w-full h-auto md:flex-col md:w-auto md:h-full bg-secondary"
+            >
+                <div class="flex flex-row md:flex-col md:w-full h-fit">
+                    <a href="/" class="hidden p-2 text-3xl md:block">
                         "Chess Or Pass"
                     </a>
 
@@ -88,10 +83,14 @@ pub fn Sidebar() -> impl IntoView {
                     </A>
                 </div>
 
-                <div class="flex flex-col gap-4 pb-8 mx-0.5 w-full h-fit">{bottom}</div>
-            </div>
+                <div class="flex flex-row gap-2 items-center mx-0.5 md:flex-col md:gap-4 md:items-stretch md:pb-8 md:w-full h-fit">
+                    {bottom}
+                </div>
+            </nav>
 
-            <Outlet />
+            <div class="flex-1 min-w-0 min-h-0">
+                <Outlet />
+            </div>
         </div>
     }
 }
@@ -99,25 +98,38 @@ pub fn Sidebar() -> impl IntoView {
 #[component]
 fn Section(#[prop(into)] text: String, #[prop(into)] image_src: String) -> impl IntoView {
     view! {
-        <div class="flex flex-row gap-3 items-center p-4 hover:bg-secondary-hover">
-            <img src=image_src class="w-8 h-8" />
+        <div class="flex flex-col gap-1 items-center p-2 text-sm md:flex-row md:gap-3 md:p-4 md:text-base hover:bg-secondary-hover">
+            <img src=image_src class="w-6 h-6 md:w-8 md:h-8" alt="" />
             {text}
         </div>
     }
 }
 
 #[server]
-async fn get_user_name(user_id: String) -> Result<String, ServerFnError> {
-    use crate::types::AppState;
+pub async fn get_user_name() -> Result<Option<String>, Error> {
+    use crate::types::{AppState, Session};
     let app_state = expect_context::<AppState>();
 
-    let mut transaction = app_state.db.pool.begin().await?;
+    let session = match Session::current(&app_state).await? {
+        Some(s) => s,
+        None => return Ok(None),
+    };
 
-    let row = sqlx::query!("SELECT username FROM users WHERE id = $1", user_id)
-        .fetch_one(&mut *transaction)
+    let row = sqlx::query!("SELECT username FROM users WHERE id = $1", session.user_id)
+        .fetch_one(&app_state.db.pool)
         .await?;
 
-    transaction.commit().await?;
+    Ok(Some(row.username))
+}
+
+#[server]
+async fn logout() -> Result<(), Error> {
+    use crate::types::{AppState, Session};
+    let app_state = expect_context::<AppState>();
+
+    if let Some(session) = Session::current(&app_state).await? {
+        session.revoke(&app_state).await?;
+    }
 
-    Ok(row.username)
+    Ok(())
 }
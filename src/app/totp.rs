@@ -0,0 +1,278 @@
+use leptos::either::{Either, EitherOf3};
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use serde::{Deserialize, Serialize};
+use shakmaty::fen::*;
+use shakmaty::san::*;
+
+use crate::app::login::get_start_position;
+use crate::app::password::PlayPassword;
+use crate::app::sidebar::get_user_name;
+use crate::types::Error;
+
+/// What the authenticator app needs to start producing codes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct TotpEnrollment {
+    secret: String,
+    uri: String,
+    /// The URI as an SVG QR code.
+    qr_code: String,
+}
+
+#[component]
+pub fn TwoFactorPage() -> impl IntoView {
+    let enabled = Resource::new(|| (), |_| totp_enabled());
+    let user = Resource::new(
+        || (),
+        |_| async move {
+            let name = get_user_name().await?.ok_or(Error::NotLoggedIn)?;
+            let start = get_start_position(name.clone()).await?;
+            Ok::<_, Error>((name, start))
+        },
+    );
+    let (replaying, set_replaying) = signal(false);
+    let enrollment = RwSignal::new(None::<TotpEnrollment>);
+    // Sent again with the code, so turning it on is confirmed by the game as well.
+    let game = StoredValue::new(Vec::<(San, Fen)>::new());
+    let (code, set_code) = signal(String::new());
+    let (error, set_error) = signal(None::<String>);
+
+    let on_replay = move |_| {
+        set_error.set(None);
+        set_replaying.set(true);
+    };
+
+    let on_replayed = move |password: Vec<(San, Fen)>| {
+        spawn_local(async move {
+            match start_totp_enrollment(password.clone()).await {
+                Ok(e) => {
+                    game.set_value(password);
+                    enrollment.set(Some(e));
+                    set_error.set(None);
+                }
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+            set_replaying.set(false);
+        })
+    };
+
+    let on_confirm = move |_| {
+        spawn_local(async move {
+            match confirm_totp(game.get_value(), code.get_untracked()).await {
+                Ok(()) => {
+                    enrollment.set(None);
+                    game.set_value(Vec::new());
+                    set_code.set(String::new());
+                    set_error.set(None);
+                    enabled.refetch();
+                }
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+        })
+    };
+
+    let on_disable_replayed = move |password: Vec<(San, Fen)>| {
+        spawn_local(async move {
+            match disable_totp(password, code.get_untracked()).await {
+                Ok(()) => {
+                    set_error.set(None);
+                    enabled.refetch();
+                }
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+            set_code.set(String::new());
+            set_replaying.set(false);
+        })
+    };
+
+    let code_input = move || {
+        view! {
+            <input
+                placeholder="123456"
+                class="font-mono w-fit"
+                type="text"
+                inputmode="numeric"
+                autocomplete="one-time-code"
+                bind:value=(code, set_code)
+            />
+        }
+    };
+
+    let suspense = move || {
+        Suspend::new(async move {
+            let user = user.await;
+            match (enabled.await, user) {
+                (Ok(true), Ok((user_name, start))) => EitherOf3::A(move || match replaying.get() {
+                    true => Either::Left(view! {
+                        <PlayPassword
+                            user_name=user_name.clone()
+                            start=start.clone()
+                            intro="Replay your game to turn off two-factor login."
+                            allow_pgn=true
+                            on_continue=on_disable_replayed
+                        />
+                    }),
+                    false => Either::Right(view! {
+                        <span class="text-xl">"Two-factor login is on"</span>
+                        <span class="font-sans text-lg font-light">
+                            "Enter a code from your authenticator app and replay your game to turn it off."
+                        </span>
+                        {code_input}
+                        <button on:click=on_replay class="p-4 text-2xl button-secondary w-fit">
+                            "Turn off"
+                        </button>
+                    }),
+                }),
+                (Ok(false), Ok((user_name, start))) => {
+                    EitherOf3::B(move || match (replaying.get(), enrollment.get()) {
+                        (_, Some(enrollment)) => EitherOf3::A(view! {
+                            <span class="font-sans text-lg font-light">
+                                "Scan the code with your authenticator app, open the link on your phone, or type in the key. Then enter the code it shows."
+                            </span>
+                            <div inner_html=enrollment.qr_code class="p-2 bg-white rounded-md w-fit h-fit" />
+                            <a href=enrollment.uri class="underline">
+                                "Add to authenticator app"
+                            </a>
+                            <span class="font-mono text-xl break-all">{group_secret(&enrollment.secret)}</span>
+                            {code_input}
+                            <button on:click=on_confirm class="p-4 text-2xl button-primary w-fit">
+                                "Turn on"
+                            </button>
+                        }),
+                        (true, None) => EitherOf3::B(view! {
+                            <PlayPassword
+                                user_name=user_name.clone()
+                                start=start.clone()
+                                intro="Replay your game to set up two-factor login."
+                                allow_pgn=true
+                                on_continue=on_replayed
+                            />
+                        }),
+                        (false, None) => EitherOf3::C(view! {
+                            <span class="font-sans text-lg font-light">
+                                "After your game, also ask for a code from an authenticator app."
+                            </span>
+                            <button on:click=on_replay class="p-4 text-2xl button-primary w-fit">
+                                "Set up"
+                            </button>
+                        }),
+                    })
+                }
+                (Err(e), _) | (_, Err(e)) => EitherOf3::C(
+                    view! { <div class="text-red-700">"Error loading two-factor: " {e.to_string()}</div> },
+                ),
+            }
+        })
+    };
+
+    view! {
+        <div class="flex overflow-scroll flex-col gap-4 justify-start p-4 w-full h-full">
+            <span class="w-full text-3xl h-fit">"Two-factor login"</span>
+            <Transition fallback=move || view! { <div>"Loading..."</div> }>{suspense}</Transition>
+            <span class="text-red-500">{move || error.get()}</span>
+        </div>
+    }
+}
+
+/// Blocks of four, the way authenticator apps show keys.
+fn group_secret(secret: &str) -> String {
+    secret
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[server]
+async fn totp_enabled() -> Result<bool, Error> {
+    use crate::types::{AppState, Session};
+    let app_state = expect_context::<AppState>();
+
+    let session = Session::require(&app_state).await?;
+
+    let user = sqlx::query!(
+        "SELECT totp_enabled FROM users WHERE id = $1",
+        session.user_id
+    )
+    .fetch_one(&app_state.db.pool)
+    .await?;
+
+    Ok(user.totp_enabled)
+}
+
+/// Stores a new secret that only takes effect once a code for it is confirmed.
+/// The game has to be replayed first, so a stolen session can't put its own app on the account.
+#[server]
+async fn start_totp_enrollment(password: Vec<(San, Fen)>) -> Result<TotpEnrollment, Error> {
+    use crate::app::login::{reauthenticate, SecondFactor};
+    use crate::types::totp::*;
+    use crate::types::AppState;
+    let app_state = expect_context::<AppState>();
+
+    let session = reauthenticate(&app_state, &password, SecondFactor::Skip).await?;
+
+    let secret = generate_secret();
+    let user = sqlx::query!(
+        r#"
+        UPDATE users SET totp_secret = $1, totp_last_step = NULL
+        WHERE id = $2 AND NOT totp_enabled
+        RETURNING username
+        "#,
+        secret,
+        session.user_id
+    )
+    .fetch_optional(&app_state.db.pool)
+    .await?
+    .ok_or_else(|| Error::Forbidden("Two-factor login is already on".to_string()))?;
+
+    let uri = otpauth_uri(&secret, &user.username);
+    Ok(TotpEnrollment {
+        secret: encode_secret(&secret),
+        qr_code: qr_code_svg(&uri)?,
+        uri,
+    })
+}
+
+/// Turns two-factor login on with a code for the new secret, the game is replayed with it so a
+/// stolen session can't do it. Wrong codes count as failed logins.
+#[server]
+async fn confirm_totp(password: Vec<(San, Fen)>, code: String) -> Result<(), Error> {
+    use crate::app::login::{reauthenticate, SecondFactor};
+    use crate::types::AppState;
+    let app_state = expect_context::<AppState>();
+
+    let session = reauthenticate(&app_state, &password, SecondFactor::Enrolling(&code)).await?;
+
+    sqlx::query!(
+        "UPDATE users SET totp_enabled = TRUE WHERE id = $1 AND totp_secret IS NOT NULL RETURNING id",
+        session.user_id
+    )
+    .fetch_optional(&app_state.db.pool)
+    .await?
+    .ok_or_else(|| Error::Forbidden("Set up two-factor login first".to_string()))?;
+
+    Ok(())
+}
+
+/// Turns two-factor login off, which needs both the game and a current code.
+#[server]
+async fn disable_totp(password: Vec<(San, Fen)>, code: String) -> Result<(), Error> {
+    use crate::app::login::{reauthenticate, SecondFactor};
+    use crate::types::AppState;
+    let app_state = expect_context::<AppState>();
+
+    let session = reauthenticate(&app_state, &password, SecondFactor::Code(Some(&code))).await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE users SET totp_enabled = FALSE, totp_secret = NULL, totp_last_step = NULL
+        WHERE id = $1
+        "#,
+        session.user_id
+    )
+    .execute(&app_state.db.pool)
+    .await?;
+
+    Ok(())
+}
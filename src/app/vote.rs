@@ -1,19 +1,13 @@
-use std::ops::Range;
-
-use chrono::{DateTime, Utc};
 use leptos::either::Either;
 use leptos::logging::*;
 use leptos::prelude::*;
 use leptos::reactive::spawn_local;
 use leptos_router::hooks::use_navigate;
 use leptos_router::NavigateOptions;
-use shakmaty::Piece;
 
-use crate::app::chess::piece_to_img;
 use crate::app::feed::VotePiece;
-use crate::types::{Error, Pieces, Vote as VoteType};
-
-use strum::IntoEnumIterator;
+use crate::app::sidebar::get_user_name;
+use crate::types::{Error, Pieces};
 
 #[component]
 pub fn VotePage() -> impl IntoView {
@@ -21,30 +15,19 @@ pub fn VotePage() -> impl IntoView {
 
     let vote_candidates = Resource::new(move || vote_count.get(), move |_| get_vote_candidates());
 
-    let (user_id, set_user_id) = signal(None::<String>);
+    let user_name = Resource::new(|| (), |_| get_user_name());
     let (selected_first, set_selected_first) = signal(None::<bool>);
     let (reason, set_reason) = signal(String::new());
 
     Effect::new(move || {
-        use gloo::storage::{LocalStorage, Storage};
-
-        let res = LocalStorage::get("id");
-
-        if let Err(e) = &res {
-            log!("No user_id found in local storage: {e:?}");
+        if let Some(Ok(None)) = user_name.get() {
+            log!("Not logged in, redirecting");
             use_navigate()("/login-register", NavigateOptions::default());
         }
-
-        set_user_id.set(res.ok());
     });
 
     let vote_local = move |first: Pieces, second: Pieces| {
         spawn_local(async move {
-            let user_id = match user_id.get_untracked() {
-                Some(id) => id,
-                None => return,
-            };
-
             let selected_first = match selected_first.get_untracked() {
                 Some(s) => s,
                 None => return,
@@ -56,7 +39,7 @@ pub fn VotePage() -> impl IntoView {
                 Some(reason.get_untracked())
             };
 
-            vote(user_id, first, second, selected_first, reason)
+            vote(first, second, selected_first, reason)
                 .await
                 .unwrap_or_else(|e| error!("Error voting: {e}"));
 
@@ -76,7 +59,7 @@ pub fn VotePage() -> impl IntoView {
             };
 
             Either::Right(view! {
-                <div class="flex flex-row gap-4 justify-center items-center p-4 rounded-2xl w-fit h-fit bg-secondary">
+                <div class="flex flex-col gap-4 justify-center items-center p-4 rounded-2xl sm:flex-row w-fit h-fit bg-secondary">
                     <VotePiece
                         piece=first.into()
                         voted_for=selected_first
@@ -95,12 +78,12 @@ pub fn VotePage() -> impl IntoView {
                 </div>
                 <input
                     placeholder="Reason (optional)"
-                    class="mx-8 w-100"
+                    class="w-full sm:mx-8 sm:w-100"
                     type="text"
                     bind:value=(reason, set_reason)
                 />
                 <button
-                    class="text-3xl rounded-md button-primary w-70 h-25"
+                    class="w-full text-3xl rounded-md sm:w-70 button-primary h-25"
                     on:click=move |_| { vote_local(first, second) }
                 >
                     "Submit!"
@@ -110,7 +93,7 @@ pub fn VotePage() -> impl IntoView {
     };
 
     view! {
-        <div class="flex flex-col gap-6 justify-center items-center p-4 w-full h-full">
+        <div class="flex overflow-y-auto flex-col gap-6 justify-start items-center p-4 w-full h-full sm:justify-center">
             {suspense}
         </div>
     }
@@ -119,6 +102,7 @@ pub fn VotePage() -> impl IntoView {
 #[server]
 async fn get_vote_candidates() -> Result<(Pieces, Pieces), Error> {
     use rand::seq::IndexedRandom;
+    use strum::IntoEnumIterator;
 
     let all_possibilities = Pieces::iter().collect::<Vec<_>>();
 
@@ -137,18 +121,19 @@ async fn get_vote_candidates() -> Result<(Pieces, Pieces), Error> {
 
 #[server]
 async fn vote(
-    user_id: String,
     first_piece: Pieces,
     second_piece: Pieces,
     voted_for_first: bool,
     reason: Option<String>,
 ) -> Result<(), Error> {
-    use crate::types::AppState;
+    use crate::types::{AppState, Session};
     let app_state = expect_context::<AppState>();
 
+    let session = Session::require(&app_state).await?;
+
     let mut transaction = app_state.db.pool.begin().await?;
 
-    let current_time = Utc::now();
+    let current_time = chrono::Utc::now();
     let vote_id = cuid2::cuid();
 
     sqlx::query!(
@@ -157,7 +142,7 @@ async fn vote(
         VALUES ($1, $2, $3, $4, $5, $6, $7)
         "#,
         vote_id,
-        user_id,
+        session.user_id,
         first_piece as Pieces,
         second_piece as Pieces,
         voted_for_first,
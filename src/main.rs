@@ -7,7 +7,7 @@ async fn main() {
     use leptos::prelude::*;
     use leptos_axum::{generate_route_list, LeptosRoutes};
 
-    use chess_or_pass::{app::*, types::AppState};
+    use chess_or_pass::{app::*, types::policy::PasswordPolicy, types::AppState};
 
     println!("Starting server...");
     if dotenvy::dotenv().is_err() {
@@ -15,6 +15,9 @@ async fn main() {
     };
 
     let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let session_secret = std::env::var("SESSION_SECRET").ok();
+    let pepper = std::env::var("PASSWORD_PEPPER").ok();
+    let password_policy = PasswordPolicy::from_env().expect("invalid password policy");
 
     let conf = get_configuration(None).unwrap();
     let addr = conf.leptos_options.site_addr;
@@ -22,9 +25,15 @@ async fn main() {
     // Generate the list of routes in your Leptos App
     let routes = generate_route_list(App);
 
-    let app_state = AppState::new(leptos_options.clone(), db_url)
-        .await
-        .expect("error creating app_state");
+    let app_state = AppState::new(
+        leptos_options.clone(),
+        db_url,
+        session_secret,
+        pepper,
+        password_policy,
+    )
+    .await
+    .expect("error creating app_state");
 
     let app = Router::new()
         .leptos_routes_with_context(
@@ -46,9 +55,12 @@ async fn main() {
     // `axum::Server` is a re-export of `hyper::Server`
     log!("listening on http://{}", &addr);
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(listener, app.into_make_service())
-        .await
-        .unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
 
 #[cfg(not(feature = "ssr"))]
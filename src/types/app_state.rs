@@ -1,12 +1,20 @@
+#[cfg(feature = "ssr")]
+use crate::types::policy::PasswordPolicy;
+#[cfg(feature = "ssr")]
 use crate::types::*;
 #[cfg(feature = "ssr")]
 use axum::extract::FromRef;
+#[cfg(feature = "ssr")]
+use axum_extra::extract::cookie::Key;
 
 #[cfg(feature = "ssr")]
 #[derive(FromRef, Clone, Debug)]
 pub struct AppState {
     pub db: Db,
     pub leptos_options: leptos::prelude::LeptosOptions,
+    pub session_key: Key,
+    pub pepper: Option<String>,
+    pub password_policy: PasswordPolicy,
 }
 
 #[cfg(feature = "ssr")]
@@ -14,12 +22,21 @@ impl AppState {
     pub async fn new(
         leptos_options: leptos::prelude::LeptosOptions,
         db_url: String,
+        session_secret: Option<String>,
+        pepper: Option<String>,
+        password_policy: PasswordPolicy,
     ) -> Result<Self, Error> {
         println!("Connecting to database...",);
 
         let db = Db::new(db_url).await?;
         println!("Connected to database...");
 
-        Ok(Self { db, leptos_options })
+        Ok(Self {
+            db,
+            leptos_options,
+            session_key: session_key(session_secret),
+            pepper,
+            password_policy,
+        })
     }
 }
@@ -0,0 +1,96 @@
+use chrono::{DateTime, Utc};
+
+#[derive(Copy, Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type))]
+#[cfg_attr(
+    feature = "ssr",
+    sqlx(type_name = "auth_event_kind", rename_all = "snake_case")
+)]
+pub enum AuthEventKind {
+    Registration,
+    LoginSuccess,
+    WrongPassword,
+    UnknownUser,
+    Lockout,
+    WrongSecondFactor,
+    RecoveryCodesRegenerated,
+    WrongRecoveryCode,
+    AccountRecovered,
+    PasswordChanged,
+    AccountDeleted,
+}
+
+impl AuthEventKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuthEventKind::Registration => "Account created",
+            AuthEventKind::LoginSuccess => "Logged in",
+            AuthEventKind::WrongPassword => "Wrong password",
+            AuthEventKind::UnknownUser => "Unknown user",
+            AuthEventKind::Lockout => "Locked out after too many attempts",
+            AuthEventKind::WrongSecondFactor => "Right game, wrong authenticator code",
+            AuthEventKind::RecoveryCodesRegenerated => "New recovery codes",
+            AuthEventKind::WrongRecoveryCode => "Wrong recovery code",
+            AuthEventKind::AccountRecovered => "New game set with a recovery code",
+            AuthEventKind::PasswordChanged => "Password changed",
+            AuthEventKind::AccountDeleted => "Account deleted",
+        }
+    }
+
+    pub fn is_failure(&self) -> bool {
+        !matches!(
+            self,
+            AuthEventKind::Registration
+                | AuthEventKind::LoginSuccess
+                | AuthEventKind::RecoveryCodesRegenerated
+                | AuthEventKind::AccountRecovered
+                | AuthEventKind::PasswordChanged
+                | AuthEventKind::AccountDeleted
+        )
+    }
+}
+
+/// An entry of the security history of a user.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct AuthEvent {
+    pub kind: AuthEventKind,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Writes an event for the request the current server fn is handling into the audit log.
+/// The name can come straight from a failed login, so it is cut to the length names can have.
+#[cfg(feature = "ssr")]
+pub async fn record_auth_event(
+    db: &crate::types::Db,
+    user_id: Option<&str>,
+    username: &str,
+    kind: AuthEventKind,
+) -> Result<(), crate::types::Error> {
+    use crate::types::client::{client_ip, user_agent};
+    use crate::types::username::MAX_USERNAME_LENGTH;
+
+    let username = username
+        .trim()
+        .chars()
+        .take(MAX_USERNAME_LENGTH)
+        .collect::<String>();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO auth_events (id, user_id, username, kind, ip, user_agent)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        cuid2::cuid(),
+        user_id,
+        username,
+        kind as AuthEventKind,
+        client_ip().map(|ip| ip.to_string()),
+        user_agent()
+    )
+    .execute(&db.pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use axum::extract::ConnectInfo;
+use axum::http::header::USER_AGENT;
+use axum::http::request::Parts;
+use leptos::prelude::*;
+use std::net::{IpAddr, SocketAddr};
+
+/// Longer user agents are cut off before they are stored.
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+/// The address of the client that sent the request the current server fn is handling.
+pub fn client_ip() -> Option<IpAddr> {
+    use_context::<Parts>()?
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+}
+
+/// The `User-Agent` header of the request the current server fn is handling.
+pub fn user_agent() -> Option<String> {
+    let user_agent = use_context::<Parts>()?
+        .headers
+        .get(USER_AGENT)?
+        .to_str()
+        .ok()?
+        .chars()
+        .take(MAX_USER_AGENT_LENGTH)
+        .collect();
+
+    Some(user_agent)
+}
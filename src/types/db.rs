@@ -1,3 +1,4 @@
+#[cfg(feature = "ssr")]
 use super::Error;
 
 #[cfg(feature = "ssr")]
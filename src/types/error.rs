@@ -1,7 +1,4 @@
-use leptos::{
-    prelude::*,
-    server_fn::{codec::JsonEncoding, error::IntoAppError},
-};
+use leptos::{prelude::*, server_fn::codec::JsonEncoding};
 
 #[derive(thiserror::Error, Debug, serde::Serialize, serde::Deserialize, Clone)]
 pub enum Error {
@@ -19,14 +16,36 @@ pub enum Error {
     FileSystem(String),
     #[error("A entry was not found: {0}")]
     DoesNotExist(String),
+    #[error("The server is not configured correctly: {0}")]
+    Config(String),
     #[error("Server fn error: {0}")]
     ServerFnError(ServerFnErrorErr),
     #[error("An impossible chess game was attempted")]
     ImpossibleChessGame,
     #[error("The password is incorrect")]
     WrongPassword,
-    #[error("The password is too short")]
-    PasswordTooShort,
+    #[error("The password is too easy to guess")]
+    WeakPassword,
+    #[error("{0}")]
+    PasswordPolicy(String),
+    #[error("That start position can't be played: {0}")]
+    InvalidStartPosition(String),
+    #[error("Could not read the PGN: {0}")]
+    InvalidPgn(String),
+    #[error("{0}")]
+    InvalidUsername(String),
+    #[error("You need to be logged in to do this")]
+    NotLoggedIn,
+    #[error("Error while hashing the password: {0}")]
+    Hash(String),
+    #[error("The recovery code is wrong or was already used")]
+    InvalidRecoveryCode,
+    #[error("Enter the code from your authenticator app")]
+    SecondFactorRequired,
+    #[error("The code from the authenticator app is wrong or was already used")]
+    WrongSecondFactor,
+    #[error("Too many login attempts, try again in {retry_after} seconds")]
+    TooManyAttempts { retry_after: u64 },
 }
 
 #[cfg(feature = "ssr")]
@@ -39,6 +58,23 @@ impl From<sqlx::Error> for Error {
     }
 }
 
+#[cfg(feature = "ssr")]
+impl axum::response::IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        use axum::http::StatusCode;
+
+        let status = match self {
+            Error::NotLoggedIn => StatusCode::UNAUTHORIZED,
+            Error::Forbidden(_) => StatusCode::FORBIDDEN,
+            Error::DoesNotExist(_) => StatusCode::NOT_FOUND,
+            Error::TooManyAttempts { .. } => StatusCode::TOO_MANY_REQUESTS,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status, self.to_string()).into_response()
+    }
+}
+
 impl FromServerFnError for Error {
     type Encoder = JsonEncoding;
 
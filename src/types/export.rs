@@ -0,0 +1,18 @@
+use crate::types::Vote;
+
+/// Everything the site keeps about a user, for the "export my data" download.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct DataExport {
+    pub profile: Profile,
+    pub votes: Vec<Vote>,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Profile {
+    pub id: String,
+    pub username: String,
+    pub variant: String,
+    /// Only set when the password game doesn't start from the normal position.
+    pub start_fen: Option<String>,
+    pub two_factor_enabled: bool,
+}
@@ -1,11 +1,36 @@
 pub mod app_state;
+pub mod auth_event;
+#[cfg(feature = "ssr")]
+pub mod client;
 pub mod db;
 pub mod error;
+pub mod export;
+#[cfg(feature = "ssr")]
+pub mod password;
+pub mod pgn;
 pub mod pieces;
+pub mod policy;
+#[cfg(feature = "ssr")]
+pub mod session;
+pub mod session_info;
+pub mod start;
+pub mod strength;
+#[cfg(feature = "ssr")]
+pub mod throttle;
+#[cfg(feature = "ssr")]
+pub mod totp;
+pub mod username;
 pub mod vote;
 
+#[cfg(feature = "ssr")]
 pub use app_state::*;
+pub use auth_event::*;
+#[cfg(feature = "ssr")]
 pub use db::*;
 pub use error::*;
+pub use export::*;
 pub use pieces::*;
+#[cfg(feature = "ssr")]
+pub use session::*;
+pub use session_info::*;
 pub use vote::*;
@@ -0,0 +1,107 @@
+use crate::types::start::StartPosition;
+use crate::types::Error;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use shakmaty::fen::Fen;
+use shakmaty::uci::UciMove;
+use shakmaty::variant::Variant;
+use shakmaty::Move;
+
+/// One SHA-256 per position, salted with a short cuid. Only kept around to verify old rows.
+pub const LEGACY_HASH_VERSION: i16 = 1;
+/// One Argon2id hash over the whole game, with a random salt and the optional server pepper.
+pub const HASH_VERSION: i16 = 2;
+
+/// The string that gets hashed for a game, the moves in UCI notation separated by spaces.
+/// Games in another variant or from another start position are prefixed with the variant and the FEN,
+/// and write castling as king takes rook, so the same moves never hash the same for two different starts.
+pub fn canonical_game(start: &StartPosition, moves: &[Move]) -> String {
+    let moves = moves
+        .iter()
+        .map(|m| match start.is_standard() {
+            true => UciMove::from_standard(*m),
+            false => UciMove::from_chess960(*m),
+        })
+        .map(|m| m.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let variant = (start.variant != Variant::Chess).then(|| start.variant.uci().to_string());
+    let fen = start.fen.as_ref().map(|fen| fen.to_string());
+
+    variant
+        .into_iter()
+        .chain(fen)
+        .chain([moves])
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Hashes the canonical game into a PHC string, which also contains the salt and the parameters.
+pub async fn hash_game(game: String, pepper: Option<String>) -> Result<String, Error> {
+    tokio::task::spawn_blocking(move || {
+        use rand::RngCore;
+
+        let mut salt = [0u8; 16];
+        rand::rng().fill_bytes(&mut salt);
+        let salt = SaltString::encode_b64(&salt).map_err(hash_error)?;
+
+        let hash = argon2(pepper.as_deref())?
+            .hash_password(game.as_bytes(), &salt)
+            .map_err(hash_error)?
+            .to_string();
+
+        Ok(hash)
+    })
+    .await
+    .map_err(|e| Error::Hash(e.to_string()))?
+}
+
+pub async fn verify_game(
+    game: String,
+    hash: String,
+    pepper: Option<String>,
+) -> Result<bool, Error> {
+    tokio::task::spawn_blocking(move || {
+        let hash = PasswordHash::new(&hash).map_err(hash_error)?;
+
+        match argon2(pepper.as_deref())?.verify_password(game.as_bytes(), &hash) {
+            Ok(()) => Ok(true),
+            Err(argon2::password_hash::Error::Password) => Ok(false),
+            Err(e) => Err(hash_error(e)),
+        }
+    })
+    .await
+    .map_err(|e| Error::Hash(e.to_string()))?
+}
+
+pub fn hash_fen_with_salt(fen: Vec<Fen>, salt: &str) -> Vec<Vec<u8>> {
+    use sha2::{Digest, Sha256};
+
+    fen.into_iter()
+        .map(|f| f.to_string() + salt)
+        .map(|s| Sha256::digest(s.as_bytes()))
+        .map(|d| d.to_vec())
+        .collect::<Vec<_>>()
+}
+
+fn argon2(pepper: Option<&str>) -> Result<Argon2<'_>, Error> {
+    match pepper {
+        Some(pepper) => Argon2::new_with_secret(
+            pepper.as_bytes(),
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::default(),
+        )
+        .map_err(|e| Error::Hash(e.to_string())),
+        None => Ok(Argon2::new(
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::default(),
+        )),
+    }
+}
+
+fn hash_error(e: argon2::password_hash::Error) -> Error {
+    Error::Hash(e.to_string())
+}
@@ -0,0 +1,206 @@
+use crate::types::start::StartPosition;
+use crate::types::Error;
+use shakmaty::fen::Fen;
+use shakmaty::san::{San, SanError, SanPlus};
+use shakmaty::variant::Variant;
+use shakmaty::{Color, EnPassantMode, Position};
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+/// Reads the main line of a PGN into the same moves and positions the board produces,
+/// and returns the start it was played from. That is `start`, unless the `[Variant]` and
+/// `[FEN]` tags say otherwise. Other tags, comments, variations, move numbers, NAGs and the
+/// result are skipped.
+pub fn parse_pgn(
+    start: &StartPosition,
+    pgn: &str,
+) -> Result<(StartPosition, Vec<(San, Fen)>), Error> {
+    let start = tagged_start(start, pgn)?;
+    let mut pos = start.position()?;
+    let mut game = vec![];
+
+    for token in main_line(pgn)? {
+        let number = match pos.turn() {
+            Color::White => format!("{}.", pos.fullmoves()),
+            Color::Black => format!("{}...", pos.fullmoves()),
+        };
+
+        let san = token.trim_end_matches(['!', '?']);
+        let san = SanPlus::from_ascii(san.as_bytes())
+            .map_err(|_| Error::InvalidPgn(format!("{number} {token} is not a move")))?
+            .san;
+
+        let m = san.to_move(&pos).map_err(|e| {
+            Error::InvalidPgn(match e {
+                SanError::IllegalSan => format!("{number} {token} can't be played here"),
+                SanError::AmbiguousSan => {
+                    format!("{number} {token} could be more than one move")
+                }
+            })
+        })?;
+
+        pos = pos
+            .play(m)
+            .map_err(|e| Error::InvalidPgn(format!("{number} {token}: {e}")))?;
+        game.push((san, Fen::from_position(&pos, EnPassantMode::Legal)));
+    }
+
+    if game.is_empty() {
+        return Err(Error::InvalidPgn("There are no moves in it".to_string()));
+    }
+
+    Ok((start, game))
+}
+
+/// The start from the `[Variant]` and `[FEN]` tags, with `start` filling in what they leave out.
+fn tagged_start(start: &StartPosition, pgn: &str) -> Result<StartPosition, Error> {
+    let variant = match tag(pgn, "Variant") {
+        Some(name) => Variant::from_ascii(name.as_bytes())
+            .map_err(|_| Error::InvalidPgn(format!("The variant {name} is unknown")))?,
+        None => start.variant,
+    };
+    let fen = match tag(pgn, "FEN") {
+        Some(fen) => Some(
+            fen.parse::<Fen>()
+                .map_err(|e| Error::InvalidPgn(format!("The FEN tag can't be read: {e}")))?,
+        ),
+        None if variant == start.variant => start.fen.clone(),
+        None => None,
+    };
+
+    let tagged = StartPosition { variant, fen };
+    tagged.position()?;
+    Ok(tagged)
+}
+
+/// The value of a tag pair like `[FEN "..."]`, from the tags before the moves.
+fn tag<'a>(pgn: &'a str, name: &str) -> Option<&'a str> {
+    pgn.lines()
+        .map(str::trim)
+        .take_while(|line| line.is_empty() || line.starts_with('['))
+        .flat_map(|line| line.split(']'))
+        .filter_map(|pair| {
+            let (tag, value) = pair
+                .trim()
+                .strip_prefix('[')?
+                .split_once(char::is_whitespace)?;
+            Some((tag, value.trim().strip_prefix('"')?.strip_suffix('"')?))
+        })
+        .find(|(tag, _)| *tag == name)
+        .map(|(_, value)| value)
+}
+
+/// Splits the PGN into the moves of the main line, still with check marks and annotations.
+fn main_line(pgn: &str) -> Result<Vec<&str>, Error> {
+    let mut tokens = vec![];
+    let mut depth = 0usize;
+    let mut token_start = None;
+    let mut chars = pgn.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let skip_until = match c {
+            '{' => Some('}'),
+            '[' if depth == 0 => Some(']'),
+            ';' => Some('\n'),
+            _ => None,
+        };
+
+        if c.is_whitespace() || skip_until.is_some() || c == '(' || c == ')' {
+            if let Some(start) = token_start.take() {
+                if depth == 0 {
+                    tokens.push(&pgn[start..i]);
+                }
+            }
+        } else if token_start.is_none() {
+            token_start = Some(i);
+        }
+
+        if let Some(end) = skip_until {
+            if !chars.by_ref().any(|(_, c)| c == end) && end != '\n' {
+                return Err(Error::InvalidPgn(format!("A {c} is never closed")));
+            }
+        }
+
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth = depth.checked_sub(1).ok_or_else(|| {
+                    Error::InvalidPgn("A ) closes a variation that was never opened".to_string())
+                })?
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(start) = token_start {
+        if depth == 0 {
+            tokens.push(&pgn[start..]);
+        }
+    }
+
+    if depth > 0 {
+        return Err(Error::InvalidPgn("A ( is never closed".to_string()));
+    }
+
+    Ok(tokens
+        .into_iter()
+        .filter(|token| !RESULTS.contains(token))
+        .filter(|token| !token.starts_with('$'))
+        .map(strip_move_number)
+        .filter(|token| !token.is_empty())
+        .collect())
+}
+
+/// `12.e4` and `12...e5` become the move, `12.` and `...` become empty.
+fn strip_move_number(token: &str) -> &str {
+    let rest = token.trim_start_matches(|c: char| c.is_ascii_digit());
+    match rest.starts_with('.') {
+        true => rest.trim_start_matches('.'),
+        false => token,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_without_tags_start_from_the_given_position() {
+        let (start, game) = parse_pgn(&StartPosition::default(), "1. e4 e5 2. Nf3 *").unwrap();
+
+        assert_eq!(start, StartPosition::default());
+        assert_eq!(game.len(), 3);
+    }
+
+    #[test]
+    fn tags_set_the_start() {
+        let pgn = r#"[Event "Casual"]
+[Variant "Chess960"]
+[FEN "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"]
+
+1. e4 e5 2. Nf3 *"#;
+        let (start, game) = parse_pgn(&StartPosition::default(), pgn).unwrap();
+
+        assert_eq!(start.variant, Variant::Chess);
+        assert_eq!(
+            start.fen.map(|fen| fen.to_string()),
+            Some("bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1".to_string())
+        );
+        assert_eq!(game.len(), 3);
+
+        let pgn = "[Variant \"King of the Hill\"]\n\n1. e4 e5 *";
+        let (start, _) = parse_pgn(&StartPosition::default(), pgn).unwrap();
+        assert_eq!(start.variant, Variant::KingOfTheHill);
+        assert_eq!(start.fen, None);
+    }
+
+    #[test]
+    fn unknown_variants_are_refused() {
+        let pgn = "[Variant \"Fischer Random Plus\"]\n\n1. e4 *";
+
+        assert!(matches!(
+            parse_pgn(&StartPosition::default(), pgn),
+            Err(Error::InvalidPgn(_))
+        ));
+    }
+}
@@ -0,0 +1,124 @@
+use crate::types::Error;
+use shakmaty::fen::{Epd, Fen};
+use shakmaty::san::San;
+use shakmaty::variant::VariantPosition;
+use shakmaty::{EnPassantMode, Outcome, Position};
+use std::collections::HashMap;
+
+pub const DEFAULT_MIN_PLIES: usize = 4;
+
+/// The rules a new password game has to follow. Every deployment sets its own,
+/// games that were made under older rules keep working.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct PasswordPolicy {
+    pub min_plies: usize,
+    pub max_plies: Option<usize>,
+    /// The game has to end in checkmate, stalemate or another result the board knows about.
+    pub require_outcome: bool,
+    /// How often the same position may come up, so games can't be padded with shuffling pieces.
+    pub max_repetitions: Option<usize>,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_plies: DEFAULT_MIN_PLIES,
+            max_plies: None,
+            require_outcome: false,
+            max_repetitions: None,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Reads `PASSWORD_MIN_PLIES`, `PASSWORD_MAX_PLIES`, `PASSWORD_REQUIRE_OUTCOME`
+    /// and `PASSWORD_MAX_REPETITIONS`, anything unset keeps its default.
+    #[cfg(feature = "ssr")]
+    pub fn from_env() -> Result<Self, Error> {
+        fn var<T: std::str::FromStr>(name: &str) -> Result<Option<T>, Error> {
+            std::env::var(name)
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+                .map(|value| {
+                    value
+                        .trim()
+                        .parse()
+                        .map_err(|_| Error::Config(format!("{name} can't be set to {value:?}")))
+                })
+                .transpose()
+        }
+
+        let default = Self::default();
+        let policy = Self {
+            min_plies: var("PASSWORD_MIN_PLIES")?.unwrap_or(default.min_plies),
+            max_plies: var("PASSWORD_MAX_PLIES")?.or(default.max_plies),
+            require_outcome: var("PASSWORD_REQUIRE_OUTCOME")?.unwrap_or(default.require_outcome),
+            max_repetitions: var("PASSWORD_MAX_REPETITIONS")?.or(default.max_repetitions),
+        };
+
+        // No game could ever pass, so nobody could register.
+        if let Some(max) = policy.max_plies.filter(|max| *max < policy.min_plies) {
+            return Err(Error::Config(format!(
+                "PASSWORD_MAX_PLIES ({max}) can't be below PASSWORD_MIN_PLIES ({})",
+                policy.min_plies
+            )));
+        }
+
+        Ok(policy)
+    }
+
+    /// The rules in words, for showing them before the game is played.
+    pub fn requirements(&self) -> Vec<String> {
+        let mut requirements = vec![match self.max_plies {
+            Some(max) => format!("Between {} and {max} moves", self.min_plies),
+            None => format!("At least {} moves", self.min_plies),
+        }];
+        if self.require_outcome {
+            requirements.push("The game has to be over".to_string());
+        }
+        if let Some(max) = self.max_repetitions {
+            requirements.push(format!("No position more than {max} times"));
+        }
+        requirements
+    }
+
+    /// Replays the game from `start` and returns the first rule it breaks.
+    pub fn check(&self, start: &VariantPosition, game: &[(San, Fen)]) -> Result<(), Error> {
+        let broken = |rule: String| Err(Error::PasswordPolicy(rule));
+
+        if game.len() < self.min_plies {
+            return broken(format!("Play at least {} moves", self.min_plies));
+        }
+        if let Some(max) = self.max_plies.filter(|max| game.len() > *max) {
+            return broken(format!("Play at most {max} moves"));
+        }
+
+        let mut pos = start.clone();
+        let mut seen = HashMap::<String, usize>::new();
+        let mut count = |pos: &VariantPosition| {
+            let times = seen
+                .entry(Epd::from_position(pos, EnPassantMode::Legal).to_string())
+                .or_default();
+            *times += 1;
+            *times
+        };
+        let mut most_repeated = count(&pos);
+
+        for (san, _fen) in game {
+            let m = san.to_move(&pos).map_err(|_| Error::ImpossibleChessGame)?;
+            pos = pos.play(m).map_err(|_| Error::ImpossibleChessGame)?;
+            most_repeated = most_repeated.max(count(&pos));
+        }
+
+        if let Some(max) = self.max_repetitions.filter(|max| most_repeated > *max) {
+            return broken(format!(
+                "Don't reach the same position more than {max} times"
+            ));
+        }
+        if self.require_outcome && !matches!(pos.outcome(), Outcome::Known(_)) {
+            return broken("Play until the game is over".to_string());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use crate::types::{AppState, Error};
+use axum::extract::{FromRequestParts, OptionalFromRequestParts};
+use axum::http::request::Parts;
+use axum_extra::extract::cookie::{Cookie, Key, SameSite, SignedCookieJar};
+use leptos::prelude::*;
+
+pub const SESSION_COOKIE: &str = "session";
+pub const SESSION_LIFETIME_DAYS: i64 = 30;
+/// How stale `last_seen_at` may get before a request updates it, so not every request writes.
+const LAST_SEEN_RESOLUTION_SECS: i64 = 60;
+
+/// A logged in user, resolved from the signed session cookie of the request.
+#[derive(Clone, Debug)]
+pub struct Session {
+    pub id: String,
+    pub user_id: String,
+}
+
+impl Session {
+    /// Creates a new session for the user and attaches the cookie to the current server fn response.
+    pub async fn start(app_state: &AppState, user_id: &str) -> Result<Self, Error> {
+        use crate::types::client::user_agent;
+        use rand::distr::{Alphanumeric, SampleString};
+
+        let id = cuid2::cuid();
+        let token = Alphanumeric.sample_string(&mut rand::rng(), 48);
+        let expires_at = chrono::Utc::now() + chrono::Duration::days(SESSION_LIFETIME_DAYS);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO sessions (id, user_id, token_hash, expires_at, user_agent)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            id,
+            user_id,
+            hash_token(&token),
+            expires_at,
+            user_agent()
+        )
+        .execute(&app_state.db.pool)
+        .await?;
+
+        let cookie = Cookie::build((SESSION_COOKIE, token))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .secure(app_state.leptos_options.env == leptos::config::Env::PROD)
+            .max_age(time::Duration::days(SESSION_LIFETIME_DAYS))
+            .build();
+
+        set_cookies(SignedCookieJar::new(app_state.session_key.clone()).add(cookie));
+
+        Ok(Self {
+            id,
+            user_id: user_id.to_string(),
+        })
+    }
+
+    /// Looks up the session of the request that the current server fn is handling.
+    pub async fn current(app_state: &AppState) -> Result<Option<Self>, Error> {
+        let mut parts = use_context::<Parts>().ok_or(Error::ServerFnError(
+            ServerFnErrorErr::ServerError("Request parts are missing from the context".to_string()),
+        ))?;
+
+        <Self as OptionalFromRequestParts<AppState>>::from_request_parts(&mut parts, app_state)
+            .await
+    }
+
+    /// Same as [`Session::current`], but fails with [`Error::NotLoggedIn`] when there is no session.
+    pub async fn require(app_state: &AppState) -> Result<Self, Error> {
+        Self::current(app_state).await?.ok_or(Error::NotLoggedIn)
+    }
+
+    /// Deletes the session from the database and clears the cookie.
+    pub async fn revoke(self, app_state: &AppState) -> Result<(), Error> {
+        sqlx::query!("DELETE FROM sessions WHERE id = $1", self.id)
+            .execute(&app_state.db.pool)
+            .await?;
+
+        set_cookies(
+            SignedCookieJar::new(app_state.session_key.clone())
+                .remove(Cookie::build(SESSION_COOKIE).path("/")),
+        );
+
+        Ok(())
+    }
+}
+
+impl OptionalFromRequestParts<AppState> for Session {
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        let jar = match SignedCookieJar::<Key>::from_request_parts(parts, state).await {
+            Ok(jar) => jar,
+            Err(e) => match e {},
+        };
+
+        let token = match jar.get(SESSION_COOKIE) {
+            Some(cookie) => cookie.value().to_string(),
+            None => return Ok(None),
+        };
+
+        let session = sqlx::query!(
+            "SELECT id, user_id, last_seen_at FROM sessions WHERE token_hash = $1 AND expires_at > NOW()",
+            hash_token(&token)
+        )
+        .fetch_optional(&state.db.pool)
+        .await?;
+
+        let session = match session {
+            Some(session) => session,
+            None => return Ok(None),
+        };
+
+        if chrono::Utc::now() - session.last_seen_at
+            > chrono::Duration::seconds(LAST_SEEN_RESOLUTION_SECS)
+        {
+            sqlx::query!(
+                "UPDATE sessions SET last_seen_at = NOW() WHERE id = $1",
+                session.id
+            )
+            .execute(&state.db.pool)
+            .await?;
+        }
+
+        Ok(Some(Session {
+            id: session.id,
+            user_id: session.user_id,
+        }))
+    }
+}
+
+impl FromRequestParts<AppState> for Session {
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        <Self as OptionalFromRequestParts<AppState>>::from_request_parts(parts, state)
+            .await?
+            .ok_or(Error::NotLoggedIn)
+    }
+}
+
+/// Builds the cookie signing key from the `SESSION_SECRET`, or a random one if it isn't set.
+pub fn session_key(secret: Option<String>) -> Key {
+    match secret {
+        Some(secret) => {
+            assert!(
+                secret.len() >= 32,
+                "SESSION_SECRET must be at least 32 bytes long"
+            );
+            Key::derive_from(secret.as_bytes())
+        }
+        None => {
+            eprintln!("SESSION_SECRET is not set, sessions will not survive a restart");
+            Key::generate()
+        }
+    }
+}
+
+fn hash_token(token: &str) -> Vec<u8> {
+    use sha2::Digest;
+    sha2::Sha256::digest(token.as_bytes()).to_vec()
+}
+
+fn set_cookies(jar: SignedCookieJar) {
+    use axum::http::header::SET_COOKIE;
+    use axum::response::IntoResponse;
+
+    let response_options = expect_context::<leptos_axum::ResponseOptions>();
+    for cookie in jar.into_response().headers().get_all(SET_COOKIE) {
+        response_options.append_header(SET_COOKIE, cookie.clone());
+    }
+}
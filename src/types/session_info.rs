@@ -0,0 +1,12 @@
+use chrono::{DateTime, Utc};
+
+/// A login of the current user, as listed on the sessions page.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct SessionInfo {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub user_agent: Option<String>,
+    /// Whether this is the session of the browser looking at the list.
+    pub current: bool,
+}
@@ -0,0 +1,157 @@
+use crate::types::Error;
+use shakmaty::fen::Fen;
+use shakmaty::variant::{Variant, VariantPosition};
+use shakmaty::{Board, CastlingMode, Color, EnPassantMode, Position};
+
+/// The Chess960 number of the normal chess start position.
+pub const STANDARD_CHESS960: u32 = 518;
+pub const CHESS960_POSITIONS: u32 = 960;
+
+/// Where the knights go on the five squares that are left after the bishops and the queen.
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+/// The Chess960 start position with the given Scharnagl number, `None` if it is 960 or above.
+pub fn chess960_fen(number: u32) -> Option<Fen> {
+    if number >= CHESS960_POSITIONS {
+        return None;
+    }
+
+    let mut back_rank = [None::<char>; 8];
+
+    let (n, light_bishop) = (number / 4, number % 4);
+    back_rank[2 * light_bishop as usize + 1] = Some('b');
+    let (n, dark_bishop) = (n / 4, n % 4);
+    back_rank[2 * dark_bishop as usize] = Some('b');
+    let (knights, queen) = (n / 6, n % 6);
+
+    let mut place = |index: usize, piece: char| {
+        let square = back_rank
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.is_none())
+            .nth(index)
+            .map(|(square, _)| square)
+            .expect("there are always enough empty squares");
+        back_rank[square] = Some(piece);
+    };
+
+    place(queen as usize, 'q');
+    let (first, second) = KNIGHT_PLACEMENTS[knights as usize];
+    // The second knight moves one to the left once the first one is placed.
+    place(first, 'n');
+    place(second - 1, 'n');
+    place(0, 'r');
+    place(0, 'k');
+    place(0, 'r');
+
+    let black = back_rank.iter().flatten().collect::<String>();
+    let white = black.to_uppercase();
+
+    format!("{black}/pppppppp/8/8/8/8/PPPPPPPP/{white} w KQkq - 0 1")
+        .parse()
+        .ok()
+}
+
+/// Where a password game starts and by which rules it is played, stored with the user.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StartPosition {
+    #[serde(with = "variant_uci")]
+    pub variant: Variant,
+    /// `None` is the usual start position of the variant.
+    /// Other positions are read with Chess960 castling, so any rook setup works.
+    pub fen: Option<Fen>,
+}
+
+impl StartPosition {
+    pub fn position(&self) -> Result<VariantPosition, Error> {
+        match &self.fen {
+            None => Ok(VariantPosition::new(self.variant)),
+            Some(fen) => VariantPosition::from_setup(
+                self.variant,
+                fen.clone().into_setup(),
+                CastlingMode::Chess960,
+            )
+            .map_err(|e| Error::InvalidStartPosition(e.to_string())),
+        }
+    }
+
+    /// Reads a start position the way it is stored with the user, and checks that it can be played.
+    pub fn parse(variant: &str, fen: Option<&str>) -> Result<Self, Error> {
+        let variant = Variant::from_uci(variant)
+            .map_err(|_| Error::InvalidStartPosition(format!("Unknown variant {variant}")))?;
+        let fen = fen
+            .map(|fen| {
+                fen.trim()
+                    .parse::<Fen>()
+                    .map_err(|e| Error::InvalidStartPosition(e.to_string()))
+            })
+            .transpose()?;
+
+        let start = Self { variant, fen };
+        start.position()?;
+        Ok(start)
+    }
+
+    /// Whether both start from the same position by the same rules, however their FENs are written.
+    pub fn is_same_start(&self, other: &Self) -> bool {
+        match (self.position(), other.position()) {
+            (Ok(a), Ok(b)) => {
+                a.variant() == b.variant()
+                    && Fen::from_position(&a, EnPassantMode::Legal)
+                        == Fen::from_position(&b, EnPassantMode::Legal)
+            }
+            _ => false,
+        }
+    }
+
+    /// Normal chess from the normal start, the only games that were possible before variants.
+    pub fn is_standard(&self) -> bool {
+        self.variant == Variant::Chess && self.fen.is_none()
+    }
+}
+
+/// Whether the position looks like the normal chess start, so opening books apply.
+pub fn is_standard_start(pos: &VariantPosition) -> bool {
+    pos.variant() == Variant::Chess
+        && pos.board() == &Board::default()
+        && pos.turn() == Color::White
+}
+
+pub fn variant_name(variant: Variant) -> &'static str {
+    match variant {
+        Variant::Chess => "Chess",
+        Variant::Atomic => "Atomic",
+        Variant::Antichess => "Antichess",
+        Variant::KingOfTheHill => "King of the Hill",
+        Variant::ThreeCheck => "Three-check",
+        Variant::Crazyhouse => "Crazyhouse",
+        Variant::RacingKings => "Racing Kings",
+        Variant::Horde => "Horde",
+    }
+}
+
+/// shakmaty has no serde support for [`Variant`], so it goes over the wire by its UCI name.
+mod variant_uci {
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+    use shakmaty::variant::Variant;
+
+    pub fn serialize<S: Serializer>(variant: &Variant, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(variant.uci())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Variant, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Variant::from_uci(&name).map_err(|_| D::Error::custom(format!("unknown variant {name}")))
+    }
+}
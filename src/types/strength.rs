@@ -0,0 +1,290 @@
+use crate::types::start::is_standard_start;
+use shakmaty::san::San;
+use shakmaty::variant::VariantPosition;
+use shakmaty::{Board, Color, Position};
+
+/// Games below this are rejected when registering or changing the password.
+pub const MIN_STRENGTH_BITS: f64 = 30.0;
+/// Where the meter is full.
+pub const STRONG_STRENGTH_BITS: f64 = 60.0;
+/// A move out of a well known opening is barely a choice.
+const BOOK_MOVE_BITS: f64 = 0.5;
+
+/// Well known lines, a game that starts like one of these gets little credit for those moves.
+const OPENINGS: &[(&str, &str)] = &[
+    ("Fool's Mate", "f3 e5 g4 Qh4"),
+    ("Fool's Mate", "f4 e5 g4 Qh4"),
+    ("Scholar's Mate", "e4 e5 Bc4 Nc6 Qh5 Nf6 Qxf7"),
+    ("Scholar's Mate", "e4 e5 Qh5 Nc6 Bc4 Nf6 Qxf7"),
+    (
+        "Italian Game",
+        "e4 e5 Nf3 Nc6 Bc4 Bc5 c3 Nf6 d4 exd4 cxd4 Bb4",
+    ),
+    (
+        "Two Knights Defense",
+        "e4 e5 Nf3 Nc6 Bc4 Nf6 Ng5 d5 exd5 Na5",
+    ),
+    (
+        "Ruy Lopez",
+        "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 d6 c3 O-O",
+    ),
+    ("Scotch Game", "e4 e5 Nf3 Nc6 d4 exd4 Nxd4 Nf6 Nxc6 bxc6"),
+    ("Petrov's Defense", "e4 e5 Nf3 Nf6 Nxe5 d6 Nf3 Nxe4 d4 d5"),
+    ("King's Gambit", "e4 e5 f4 exf4 Nf3 g5"),
+    ("Vienna Game", "e4 e5 Nc3 Nf6 f4 d5"),
+    (
+        "Sicilian Najdorf",
+        "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 Be3 e5",
+    ),
+    (
+        "Sicilian Dragon",
+        "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6 Be3 Bg7",
+    ),
+    ("Sicilian Alapin", "e4 c5 c3 Nf6 e5 Nd5 d4 cxd4"),
+    ("French Defense", "e4 e6 d4 d5 Nc3 Bb4 e5 c5 a3 Bxc3"),
+    ("French Advance", "e4 e6 d4 d5 e5 c5 c3 Nc6 Nf3 Qb6"),
+    ("Caro-Kann Defense", "e4 c6 d4 d5 Nc3 dxe4 Nxe4 Bf5 Ng3 Bg6"),
+    ("Scandinavian Defense", "e4 d5 exd5 Qxd5 Nc3 Qa5 d4 Nf6"),
+    ("Pirc Defense", "e4 d6 d4 Nf6 Nc3 g6 f4 Bg7"),
+    ("Alekhine's Defense", "e4 Nf6 e5 Nd5 d4 d6 Nf3 Bg4"),
+    (
+        "Queen's Gambit Declined",
+        "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3",
+    ),
+    (
+        "Queen's Gambit Accepted",
+        "d4 d5 c4 dxc4 Nf3 Nf6 e3 e6 Bxc4 c5",
+    ),
+    ("Slav Defense", "d4 d5 c4 c6 Nf3 Nf6 Nc3 dxc4 a4 Bf5"),
+    ("London System", "d4 d5 Bf4 Nf6 e3 e6 Nf3 c5 c3 Nc6"),
+    ("London System", "d4 Nf6 Bf4 d5 e3 e6 Nf3 c5 c3 Nc6"),
+    (
+        "King's Indian Defense",
+        "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5",
+    ),
+    ("Nimzo-Indian Defense", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O"),
+    (
+        "Grünfeld Defense",
+        "d4 Nf6 c4 g6 Nc3 d5 cxd5 Nxd5 e4 Nxc3 bxc3 Bg7",
+    ),
+    ("Dutch Defense", "d4 f5 g3 Nf6 Bg2 e6 Nf3 Be7"),
+    ("English Opening", "c4 e5 Nc3 Nf6 Nf3 Nc6 g3 d5"),
+    ("Réti Opening", "Nf3 d5 c4 e6 g3 Nf6 Bg2 Be7"),
+    ("Bongcloud", "e4 e5 Ke2 Ke7"),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PasswordStrength {
+    /// Roughly how many guesses it takes, as a power of two.
+    pub bits: f64,
+    /// The longest known opening the game follows, and for how many plies.
+    pub opening: Option<(&'static str, usize)>,
+    /// Plies that had only one legal move.
+    pub forced_plies: usize,
+    /// Plies that went back to a position that was already on the board.
+    pub repeated_plies: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub enum StrengthLevel {
+    VeryWeak,
+    Weak,
+    Fair,
+    Strong,
+}
+
+impl PasswordStrength {
+    /// Estimates the strength from the number of legal alternatives at every ply.
+    /// Stops counting at the first move that can't be played.
+    /// Opening books only apply to games from the normal start position.
+    pub fn estimate(start: &VariantPosition, moves: &[San]) -> Self {
+        let book = match is_standard_start(start) {
+            true => OPENINGS,
+            false => &[],
+        };
+        let opening = book
+            .iter()
+            .map(|(name, line)| {
+                let plies = line
+                    .split_whitespace()
+                    .zip(moves)
+                    .take_while(|(book, played)| *book == played.to_string())
+                    .count();
+                (*name, plies)
+            })
+            .max_by_key(|(_, plies)| *plies)
+            .filter(|(_, plies)| *plies > 0);
+        let book_plies = opening.map(|(_, plies)| plies).unwrap_or(0);
+
+        let mut pos = start.clone();
+        let mut seen: Vec<(Board, Color)> = vec![(pos.board().clone(), pos.turn())];
+        let mut strength = Self {
+            bits: 0.0,
+            opening,
+            forced_plies: 0,
+            repeated_plies: 0,
+        };
+
+        for (ply, san) in moves.iter().enumerate() {
+            let alternatives = pos.legal_moves().len();
+
+            pos = match san
+                .to_move(&pos)
+                .ok()
+                .and_then(|m| pos.clone().play(m).ok())
+            {
+                Some(p) => p,
+                None => break,
+            };
+
+            let key = (pos.board().clone(), pos.turn());
+            let repeated = seen.contains(&key);
+            seen.push(key);
+
+            if alternatives <= 1 {
+                strength.forced_plies += 1;
+            } else if repeated {
+                strength.repeated_plies += 1;
+            } else if ply < book_plies {
+                strength.bits += BOOK_MOVE_BITS;
+            } else {
+                strength.bits += (alternatives as f64).log2();
+            }
+        }
+
+        strength
+    }
+
+    pub fn level(&self) -> StrengthLevel {
+        if self.bits < MIN_STRENGTH_BITS / 2.0 {
+            StrengthLevel::VeryWeak
+        } else if self.bits < MIN_STRENGTH_BITS {
+            StrengthLevel::Weak
+        } else if self.bits < STRONG_STRENGTH_BITS {
+            StrengthLevel::Fair
+        } else {
+            StrengthLevel::Strong
+        }
+    }
+
+    pub fn is_acceptable(&self) -> bool {
+        self.bits >= MIN_STRENGTH_BITS
+    }
+}
+
+impl StrengthLevel {
+    pub fn label(&self) -> &'static str {
+        match self {
+            StrengthLevel::VeryWeak => "Very weak",
+            StrengthLevel::Weak => "Weak",
+            StrengthLevel::Fair => "Fair",
+            StrengthLevel::Strong => "Strong",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shakmaty::fen::Fen;
+    use shakmaty::variant::Variant;
+    use shakmaty::CastlingMode;
+
+    fn sans(moves: &str) -> Vec<San> {
+        moves
+            .split_whitespace()
+            .map(|m| m.parse().expect("valid san"))
+            .collect()
+    }
+
+    fn position(fen: &str) -> VariantPosition {
+        let fen = Fen::from_ascii(fen.as_bytes()).expect("valid fen");
+        VariantPosition::from_setup(Variant::Chess, fen.into_setup(), CastlingMode::Standard)
+            .expect("legal position")
+    }
+
+    fn strength(bits: f64) -> PasswordStrength {
+        PasswordStrength {
+            bits,
+            opening: None,
+            forced_plies: 0,
+            repeated_plies: 0,
+        }
+    }
+
+    #[test]
+    fn book_moves_get_little_credit() {
+        let start = VariantPosition::new(Variant::Chess);
+        let strength = PasswordStrength::estimate(&start, &sans("e4 e5 Bc4 Nc6 Qh5 Nf6 Qxf7"));
+
+        assert_eq!(strength.opening, Some(("Scholar's Mate", 7)));
+        assert_eq!(strength.bits, 7.0 * BOOK_MOVE_BITS);
+        assert!(!strength.is_acceptable());
+    }
+
+    #[test]
+    fn leaving_the_book_counts_the_alternatives() {
+        let start = VariantPosition::new(Variant::Chess);
+        let strength = PasswordStrength::estimate(&start, &sans("e4 e5 a3"));
+
+        assert_eq!(strength.opening.map(|(_, plies)| plies), Some(2));
+        // After 1. e4 e5 white has 29 legal moves.
+        assert_eq!(strength.bits, 2.0 * BOOK_MOVE_BITS + 29f64.log2());
+    }
+
+    #[test]
+    fn no_book_outside_the_standard_start() {
+        let start = VariantPosition::new(Variant::KingOfTheHill);
+        let strength = PasswordStrength::estimate(&start, &sans("e4 e5"));
+
+        assert_eq!(strength.opening, None);
+        assert_eq!(strength.bits, 20f64.log2() + 20f64.log2());
+    }
+
+    #[test]
+    fn forced_moves_count_nothing() {
+        // The rook on g1 leaves the black king only h7.
+        let start = position("7k/8/8/8/8/8/8/K5R1 b - - 0 1");
+        let strength = PasswordStrength::estimate(&start, &sans("Kh7"));
+
+        assert_eq!(strength.forced_plies, 1);
+        assert_eq!(strength.bits, 0.0);
+    }
+
+    #[test]
+    fn going_back_to_a_seen_position_counts_nothing() {
+        let start = VariantPosition::new(Variant::Chess);
+        let strength = PasswordStrength::estimate(&start, &sans("Nc3 Nc6 Nb1 Nb8 Nc3"));
+
+        assert_eq!(strength.repeated_plies, 2);
+        // Nb8 is the start again and Nc3 the position after the first move.
+        let new_positions = PasswordStrength::estimate(&start, &sans("Nc3 Nc6 Nb1"));
+        assert_eq!(strength.bits, new_positions.bits);
+    }
+
+    #[test]
+    fn stops_at_the_first_illegal_move() {
+        let start = VariantPosition::new(Variant::KingOfTheHill);
+        let legal = PasswordStrength::estimate(&start, &sans("e4"));
+        let with_illegal = PasswordStrength::estimate(&start, &sans("e4 e4 e5"));
+
+        assert_eq!(legal, with_illegal);
+    }
+
+    #[test]
+    fn acceptable_from_the_minimum() {
+        assert!(strength(MIN_STRENGTH_BITS).is_acceptable());
+        assert!(!strength(MIN_STRENGTH_BITS - 0.1).is_acceptable());
+
+        assert_eq!(strength(0.0).level(), StrengthLevel::VeryWeak);
+        assert_eq!(
+            strength(MIN_STRENGTH_BITS - 0.1).level(),
+            StrengthLevel::Weak
+        );
+        assert_eq!(strength(MIN_STRENGTH_BITS).level(), StrengthLevel::Fair);
+        assert_eq!(
+            strength(STRONG_STRENGTH_BITS).level(),
+            StrengthLevel::Strong
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use crate::types::{Db, Error};
+use chrono::Utc;
+use std::net::IpAddr;
+
+/// Failed attempts that are allowed before the first lockout.
+pub const FREE_ATTEMPTS: i32 = 3;
+/// The first lockout, every failure after it doubles the time.
+pub const BASE_LOCKOUT_SECS: i64 = 30;
+pub const MAX_LOCKOUT_SECS: i64 = 60 * 60;
+/// Failures older than this are forgotten on the next failure.
+pub const FORGET_AFTER_HOURS: i64 = 24;
+
+/// Tracks failed logins per username and per client address, so guessing games gets slow fast.
+pub struct LoginThrottle {
+    keys: Vec<String>,
+}
+
+impl LoginThrottle {
+    pub fn new(username: &str, ip: Option<IpAddr>) -> Self {
+        // Names are unique regardless of case, so "Magnus" and "magnus" share their attempts.
+        let mut keys = vec![format!("user:{}", username.to_lowercase())];
+        if let Some(ip) = ip {
+            keys.push(format!("ip:{ip}"));
+        }
+        Self { keys }
+    }
+
+    /// Counts the attempt as a failure before the game is checked, and fails with
+    /// [`Error::TooManyAttempts`] while any of the keys is locked out. The counted rows stay locked
+    /// until the attempt is stored, so parallel requests can't all get past the same count.
+    pub async fn check(&self, db: &Db) -> Result<Attempt, Error> {
+        let mut transaction = db.pool.begin().await?;
+        let mut locked_until = None;
+        let mut failures = Vec::with_capacity(self.keys.len());
+
+        for key in &self.keys {
+            let row = sqlx::query!(
+                r#"
+                INSERT INTO login_throttles (key, failures, last_failure_at) VALUES ($1, 1, NOW())
+                ON CONFLICT (key) DO UPDATE SET
+                    failures = CASE
+                        WHEN login_throttles.last_failure_at < NOW() - make_interval(hours => $2)
+                        THEN 1
+                        ELSE login_throttles.failures + 1
+                    END,
+                    last_failure_at = NOW()
+                RETURNING failures, locked_until
+                "#,
+                key,
+                FORGET_AFTER_HOURS as i32
+            )
+            .fetch_one(&mut *transaction)
+            .await?;
+
+            if let Some(until) = row.locked_until.filter(|until| *until > Utc::now()) {
+                locked_until = locked_until.max(Some(until));
+            }
+            failures.push((key, row.failures));
+        }
+
+        // Dropping the transaction takes the count back, a turned away request isn't an attempt.
+        if let Some(until) = locked_until {
+            return Err(Error::TooManyAttempts {
+                retry_after: (until - Utc::now()).num_seconds().max(1) as u64,
+            });
+        }
+
+        let mut lockout_secs = 0;
+        for (key, failures) in failures {
+            let secs = lockout_secs_after(failures);
+            if secs == 0 {
+                continue;
+            }
+
+            sqlx::query!(
+                "UPDATE login_throttles SET locked_until = NOW() + make_interval(secs => $1) WHERE key = $2",
+                secs as f64,
+                key
+            )
+            .execute(&mut *transaction)
+            .await?;
+
+            lockout_secs = lockout_secs.max(secs);
+        }
+
+        transaction.commit().await?;
+
+        Ok(Attempt { lockout_secs })
+    }
+
+    /// Takes a counted attempt back from every key, and lifts a lockout it started.
+    /// Earlier failures are kept, for checks that passed but aren't a login yet.
+    pub async fn release(&self, db: &Db) -> Result<(), Error> {
+        Self::take_back(db, &self.keys).await
+    }
+
+    /// Forgets the failures of the username after a successful login and takes the attempt back
+    /// from the address. The address keeps its earlier failures, otherwise one known account could
+    /// be used to reset it between guesses.
+    pub async fn reset(&self, db: &Db) -> Result<(), Error> {
+        sqlx::query!("DELETE FROM login_throttles WHERE key = $1", self.keys[0])
+            .execute(&db.pool)
+            .await?;
+
+        Self::take_back(db, &self.keys[1..]).await
+    }
+
+    async fn take_back(db: &Db, keys: &[String]) -> Result<(), Error> {
+        sqlx::query!(
+            r#"
+            UPDATE login_throttles SET
+                failures = GREATEST(failures - 1, 0),
+                locked_until = CASE WHEN failures - 1 <= $2 THEN NULL ELSE locked_until END
+            WHERE key = ANY($1)
+            "#,
+            keys,
+            FREE_ATTEMPTS
+        )
+        .execute(&db.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// An attempt that was counted by [`LoginThrottle::check`].
+pub struct Attempt {
+    lockout_secs: i64,
+}
+
+impl Attempt {
+    /// Returns [`Error::TooManyAttempts`] if counting this attempt started a lockout, for when it failed.
+    pub fn failed(&self) -> Result<(), Error> {
+        match self.lockout_secs {
+            0 => Ok(()),
+            secs => Err(Error::TooManyAttempts {
+                retry_after: secs as u64,
+            }),
+        }
+    }
+}
+
+/// The lockout that starts with the given number of failures, every failure after the free ones
+/// doubles it.
+fn lockout_secs_after(failures: i32) -> i64 {
+    if failures <= FREE_ATTEMPTS {
+        return 0;
+    }
+
+    BASE_LOCKOUT_SECS
+        .saturating_mul(1 << (failures - FREE_ATTEMPTS - 1).min(32))
+        .min(MAX_LOCKOUT_SECS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::PgPool;
+
+    const IP: Option<IpAddr> = Some(IpAddr::V4(std::net::Ipv4Addr::LOCALHOST));
+
+    fn db(pool: PgPool) -> Db {
+        Db {
+            pool,
+            url: String::new(),
+        }
+    }
+
+    async fn failures(db: &Db, key: &str) -> Option<i32> {
+        sqlx::query_scalar("SELECT failures FROM login_throttles WHERE key = $1")
+            .bind(key)
+            .fetch_optional(&db.pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn lockouts_double_up_to_the_maximum() {
+        assert_eq!(lockout_secs_after(FREE_ATTEMPTS), 0);
+        assert_eq!(lockout_secs_after(FREE_ATTEMPTS + 1), BASE_LOCKOUT_SECS);
+        assert_eq!(lockout_secs_after(FREE_ATTEMPTS + 2), BASE_LOCKOUT_SECS * 2);
+        assert_eq!(lockout_secs_after(FREE_ATTEMPTS + 100), MAX_LOCKOUT_SECS);
+    }
+
+    #[sqlx::test]
+    async fn parallel_attempts_share_one_count(pool: PgPool) {
+        let db = db(pool);
+
+        let attempts = (0..10)
+            .map(|_| {
+                let db = db.clone();
+                tokio::spawn(async move { LoginThrottle::new("Magnus", IP).check(&db).await })
+            })
+            .collect::<Vec<_>>();
+
+        let mut passed = 0;
+        for attempt in attempts {
+            if attempt.await.unwrap().is_ok() {
+                passed += 1;
+            }
+        }
+
+        // The attempt after the free ones still gets through, but starts the lockout.
+        assert_eq!(passed, FREE_ATTEMPTS + 1);
+        assert_eq!(failures(&db, "user:magnus").await, Some(FREE_ATTEMPTS + 1));
+    }
+
+    #[sqlx::test]
+    async fn a_login_forgets_the_name_but_not_the_address(pool: PgPool) {
+        let db = db(pool);
+        let throttle = LoginThrottle::new("magnus", IP);
+
+        for _ in 0..FREE_ATTEMPTS {
+            throttle.check(&db).await.unwrap().failed().unwrap();
+        }
+        // The attempt that would start a lockout succeeds and takes it back.
+        throttle.check(&db).await.unwrap();
+        throttle.reset(&db).await.unwrap();
+
+        assert_eq!(failures(&db, "user:magnus").await, None);
+        assert_eq!(failures(&db, "ip:127.0.0.1").await, Some(FREE_ATTEMPTS));
+
+        // So guessing another name from the address is locked out on the next failure.
+        let other = LoginThrottle::new("hikaru", IP).check(&db).await.unwrap();
+        assert!(matches!(other.failed(), Err(Error::TooManyAttempts { .. })));
+    }
+
+    #[sqlx::test]
+    async fn released_attempts_keep_earlier_failures(pool: PgPool) {
+        let db = db(pool);
+        let throttle = LoginThrottle::new("magnus", IP);
+
+        throttle.check(&db).await.unwrap();
+        throttle.check(&db).await.unwrap();
+        throttle.release(&db).await.unwrap();
+
+        assert_eq!(failures(&db, "user:magnus").await, Some(1));
+        assert_eq!(failures(&db, "ip:127.0.0.1").await, Some(1));
+    }
+}
@@ -0,0 +1,81 @@
+use crate::types::Error;
+use unicode_normalization::UnicodeNormalization;
+use unicode_script::{Script, UnicodeScript};
+
+pub const MIN_USERNAME_LENGTH: usize = 3;
+pub const MAX_USERNAME_LENGTH: usize = 32;
+/// Allowed besides letters and digits.
+const USERNAME_PUNCTUATION: [char; 3] = ['_', '-', '.'];
+/// Names that look official, and the pages of the site.
+const RESERVED_USERNAMES: &[&str] = &[
+    "admin",
+    "administrator",
+    "root",
+    "moderator",
+    "mod",
+    "support",
+    "system",
+    "deleted",
+    "chessorpass",
+    "feed",
+    "play",
+    "game",
+    "login",
+    "register",
+    "login-register",
+    "recover",
+    "settings",
+    "api",
+    "pkg",
+];
+
+/// Trims the name and brings it into NFKC, so look-alike spellings of the same name end up equal.
+/// Enough to look a user up, older names don't have to follow the rules of [`normalize_username`].
+pub fn lookup_username(name: &str) -> String {
+    name.trim().nfkc().collect()
+}
+
+/// The name from [`lookup_username`], checked against the rules for new names.
+/// Errors say which rule the name broke.
+pub fn normalize_username(name: &str) -> Result<String, Error> {
+    let name = lookup_username(name);
+    let invalid = |rule: String| Err(Error::InvalidUsername(rule));
+
+    let length = name.chars().count();
+    if length < MIN_USERNAME_LENGTH {
+        return invalid(format!(
+            "Names need at least {MIN_USERNAME_LENGTH} characters"
+        ));
+    }
+    if length > MAX_USERNAME_LENGTH {
+        return invalid(format!(
+            "Names can have at most {MAX_USERNAME_LENGTH} characters"
+        ));
+    }
+
+    if let Some(c) = name
+        .chars()
+        .find(|c| !c.is_alphanumeric() && !USERNAME_PUNCTUATION.contains(c))
+    {
+        return invalid(format!(
+            "Names can only have letters, digits, _, - and ., not {c:?}"
+        ));
+    }
+
+    // Latin a and Cyrillic а look the same, so one name sticks to one alphabet.
+    let mut scripts = name
+        .chars()
+        .map(|c| c.script())
+        .filter(|script| !matches!(script, Script::Common | Script::Inherited));
+    if let Some(first) = scripts.next() {
+        if scripts.any(|script| script != first) {
+            return invalid("Names can't mix letters from different alphabets".to_string());
+        }
+    }
+
+    if RESERVED_USERNAMES.contains(&name.to_lowercase().as_str()) {
+        return invalid(format!("The name {name} is reserved"));
+    }
+
+    Ok(name)
+}